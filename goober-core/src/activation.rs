@@ -1,12 +1,22 @@
+use crate::Vector;
+
+/// Element-wise activation function.
 pub trait Activation: Copy {
+    /// Name recorded in the shapes of layers using this activation.
+    const NAME: &'static str;
+
     fn activate(x: f32) -> f32;
 
+    /// Derivative of `activate`, evaluated at the pre-activation input `x`
+    /// (not at the output `activate(x)`).
     fn derivative(x: f32) -> f32;
 }
 
 #[derive(Clone, Copy)]
 pub struct Identity;
 impl Activation for Identity {
+    const NAME: &'static str = "Identity";
+
     fn activate(x: f32) -> f32 {
         x
     }
@@ -19,6 +29,8 @@ impl Activation for Identity {
 #[derive(Clone, Copy)]
 pub struct ReLU;
 impl Activation for ReLU {
+    const NAME: &'static str = "ReLU";
+
     fn activate(x: f32) -> f32 {
         x.max(0.0)
     }
@@ -35,6 +47,8 @@ impl Activation for ReLU {
 #[derive(Clone, Copy)]
 pub struct SCReLU;
 impl Activation for SCReLU {
+    const NAME: &'static str = "SCReLU";
+
     fn activate(x: f32) -> f32 {
         let clamped = x.clamp(0.0, 1.0);
         clamped * clamped
@@ -52,6 +66,8 @@ impl Activation for SCReLU {
 #[derive(Clone, Copy)]
 pub struct Tanh;
 impl Activation for Tanh {
+    const NAME: &'static str = "Tanh";
+
     fn activate(x: f32) -> f32 {
         x.tanh()
     }
@@ -61,3 +77,275 @@ impl Activation for Tanh {
         1.0 - t * t
     }
 }
+
+/// Clipped ReLU, the standard NNUE activation.
+#[derive(Clone, Copy)]
+pub struct CReLU;
+impl Activation for CReLU {
+    const NAME: &'static str = "CReLU";
+
+    fn activate(x: f32) -> f32 {
+        x.clamp(0.0, 1.0)
+    }
+
+    fn derivative(x: f32) -> f32 {
+        if 0.0 < x && x < 1.0 {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// ReLU with a slope of `0.01` for negative inputs.
+#[derive(Clone, Copy)]
+pub struct LeakyReLU;
+impl LeakyReLU {
+    pub const SLOPE: f32 = 0.01;
+}
+impl Activation for LeakyReLU {
+    const NAME: &'static str = "LeakyReLU";
+
+    fn activate(x: f32) -> f32 {
+        if x > 0.0 {
+            x
+        } else {
+            Self::SLOPE * x
+        }
+    }
+
+    fn derivative(x: f32) -> f32 {
+        if x > 0.0 {
+            1.0
+        } else {
+            Self::SLOPE
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Sigmoid;
+impl Activation for Sigmoid {
+    const NAME: &'static str = "Sigmoid";
+
+    fn activate(x: f32) -> f32 {
+        1.0 / (1.0 + (-x).exp())
+    }
+
+    fn derivative(x: f32) -> f32 {
+        let s = Self::activate(x);
+        s * (1.0 - s)
+    }
+}
+
+/// GELU, using the usual tanh approximation.
+#[derive(Clone, Copy)]
+pub struct GELU;
+impl GELU {
+    const C: f32 = 0.797_884_6; // sqrt(2 / pi)
+    const A: f32 = 0.044_715;
+}
+impl Activation for GELU {
+    const NAME: &'static str = "GELU";
+
+    fn activate(x: f32) -> f32 {
+        let t = (Self::C * (x + Self::A * x * x * x)).tanh();
+        0.5 * x * (1.0 + t)
+    }
+
+    fn derivative(x: f32) -> f32 {
+        let t = (Self::C * (x + Self::A * x * x * x)).tanh();
+        let dt = (1.0 - t * t) * Self::C * (1.0 + 3.0 * Self::A * x * x);
+        0.5 * (1.0 + t) + 0.5 * x * dt
+    }
+}
+
+/// SiLU (Swish), `x * sigmoid(x)`.
+#[derive(Clone, Copy)]
+pub struct SiLU;
+impl Activation for SiLU {
+    const NAME: &'static str = "SiLU";
+
+    fn activate(x: f32) -> f32 {
+        x * Sigmoid::activate(x)
+    }
+
+    fn derivative(x: f32) -> f32 {
+        let s = Sigmoid::activate(x);
+        s * (1.0 + x * (1.0 - s))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Softplus;
+impl Activation for Softplus {
+    const NAME: &'static str = "Softplus";
+
+    fn activate(x: f32) -> f32 {
+        x.max(0.0) + (-x.abs()).exp().ln_1p()
+    }
+
+    fn derivative(x: f32) -> f32 {
+        Sigmoid::activate(x)
+    }
+}
+
+/// Hardswish, `x * relu6(x + 3) / 6`.
+#[derive(Clone, Copy)]
+pub struct Hardswish;
+impl Activation for Hardswish {
+    const NAME: &'static str = "Hardswish";
+
+    fn activate(x: f32) -> f32 {
+        x * (x + 3.0).clamp(0.0, 6.0) / 6.0
+    }
+
+    fn derivative(x: f32) -> f32 {
+        if x <= -3.0 {
+            0.0
+        } else if x >= 3.0 {
+            1.0
+        } else {
+            (2.0 * x + 3.0) / 6.0
+        }
+    }
+}
+
+/// Activation function applied to a whole vector at once, such as softmax.
+pub trait VectorActivation: Copy {
+    /// Name recorded in the shapes of layers using this activation.
+    const NAME: &'static str;
+
+    fn activate<const N: usize>(x: &Vector<N>) -> Vector<N>;
+
+    /// Given the output `y = activate(x)` and the error with respect to `y`,
+    /// returns the error with respect to `x`.
+    fn backprop<const N: usize>(y: &Vector<N>, err: &Vector<N>) -> Vector<N>;
+}
+
+#[derive(Clone, Copy)]
+pub struct Softmax;
+impl VectorActivation for Softmax {
+    const NAME: &'static str = "Softmax";
+
+    fn activate<const N: usize>(x: &Vector<N>) -> Vector<N> {
+        let (max, total) = log_sum_exp_parts(x);
+        Vector::from_fn(|i| (x[i] - max).exp() / total)
+    }
+
+    fn backprop<const N: usize>(y: &Vector<N>, err: &Vector<N>) -> Vector<N> {
+        let dot = y.dot(err);
+        Vector::from_fn(|i| y[i] * (err[i] - dot))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct LogSoftmax;
+impl VectorActivation for LogSoftmax {
+    const NAME: &'static str = "LogSoftmax";
+
+    fn activate<const N: usize>(x: &Vector<N>) -> Vector<N> {
+        let (max, total) = log_sum_exp_parts(x);
+        let log_total = max + total.ln();
+        Vector::from_fn(|i| x[i] - log_total)
+    }
+
+    fn backprop<const N: usize>(y: &Vector<N>, err: &Vector<N>) -> Vector<N> {
+        let mut sum = 0.0;
+        for i in 0..N {
+            sum += err[i];
+        }
+
+        Vector::from_fn(|i| err[i] - y[i].exp() * sum)
+    }
+}
+
+/// Returns the maximum of `x` and `sum(exp(x - max))`.
+fn log_sum_exp_parts<const N: usize>(x: &Vector<N>) -> (f32, f32) {
+    let mut max = f32::NEG_INFINITY;
+    for i in 0..N {
+        max = max.max(x[i]);
+    }
+
+    let mut total = 0.0;
+    for i in 0..N {
+        total += (x[i] - max).exp();
+    }
+
+    (max, total)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check<T: Activation>() {
+        let h = 0.001;
+
+        for i in -30..=30 {
+            // offset to avoid evaluating exactly at any kinks
+            let x = i as f32 * 0.1 + 0.0123;
+            let numerical = (T::activate(x + h) - T::activate(x - h)) / (2.0 * h);
+            let analytical = T::derivative(x);
+
+            assert!(
+                (numerical - analytical).abs() < 1e-2,
+                "x = {x}: numerical {numerical}, analytical {analytical}"
+            );
+        }
+    }
+
+    #[test]
+    fn derivatives() {
+        check::<Identity>();
+        check::<ReLU>();
+        check::<SCReLU>();
+        check::<Tanh>();
+        check::<CReLU>();
+        check::<LeakyReLU>();
+        check::<Sigmoid>();
+        check::<GELU>();
+        check::<SiLU>();
+        check::<Softplus>();
+        check::<Hardswish>();
+    }
+
+    fn check_vector<T: VectorActivation>() {
+        let x = Vector::from_raw([0.3, -1.2, 2.5, 0.0]);
+        let err = Vector::from_raw([1.0, -0.5, 0.25, 2.0]);
+        let back = T::backprop(&T::activate(&x), &err);
+
+        for i in 0..4 {
+            let h = 0.001;
+            let mut plus = x;
+            plus[i] += h;
+            let mut minus = x;
+            minus[i] -= h;
+
+            let numerical =
+                (T::activate(&plus).dot(&err) - T::activate(&minus).dot(&err)) / (2.0 * h);
+            assert!((numerical - back[i]).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn vector_derivatives() {
+        check_vector::<Softmax>();
+        check_vector::<LogSoftmax>();
+
+        let probs = Softmax::activate(&Vector::from_raw([1000.0, 1000.0]));
+        assert_eq!(probs, Vector::from_raw([0.5, 0.5]));
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(CReLU::activate(1.5), 1.0);
+        assert_eq!(LeakyReLU::activate(-2.0), -0.02);
+        assert_eq!(Sigmoid::activate(0.0), 0.5);
+        assert_eq!(GELU::activate(0.0), 0.0);
+        assert!((GELU::activate(1.0) - 0.841_192).abs() < 1e-4);
+        assert!((Softplus::activate(0.0) - 2f32.ln()).abs() < 1e-6);
+        assert_eq!(Hardswish::activate(4.0), 4.0);
+        assert_eq!(Hardswish::activate(-4.0), 0.0);
+    }
+}
@@ -0,0 +1,325 @@
+//! Self-describing binary format used to save and load networks.
+//!
+//! All values are little-endian. A file is laid out as
+//! - the magic bytes `GOOBERNN`
+//! - the format version, as a `u32`
+//! - the number of layer shapes, as a `u32`, followed by each shape
+//!   (kind length as a `u32`, kind bytes, number of dims as a `u32`, each dim as a `u64`)
+//! - the number of parameters, as a `u64`, followed by each parameter as an `f32`
+//! - an FNV-1a checksum of everything before it, as a `u64`
+
+use crate::FeedForwardNetwork;
+
+pub const MAGIC: [u8; 8] = *b"GOOBERNN";
+pub const VERSION: u32 = 1;
+
+/// Shape of a single layer, as recorded in the header of a saved network.
+///
+/// Only `kind` and `dims` are saved, `name`, `offset` and `len` locate the
+/// layer's parameters within the network for diagnostics.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayerShape {
+    /// Type of the layer, including its activation, e.g. `DenseConnected<ReLU>`.
+    pub kind: String,
+    pub dims: Vec<usize>,
+    /// Path of fields leading to the layer, e.g. `l1.a`.
+    pub name: String,
+    /// Index of the first parameter of the layer within the network.
+    pub offset: usize,
+    /// Number of parameters in the layer.
+    pub len: usize,
+}
+
+impl LayerShape {
+    pub fn new<L>(kind: &str, dims: &[usize]) -> Self {
+        Self {
+            kind: kind.to_string(),
+            dims: dims.to_vec(),
+            name: String::new(),
+            offset: 0,
+            len: std::mem::size_of::<L>() / std::mem::size_of::<f32>(),
+        }
+    }
+
+    /// Places the layer within field `field` of a parent network, that
+    /// starts `offset` bytes into the parent.
+    pub fn nest(&mut self, field: &str, offset: usize) {
+        self.name = if self.name.is_empty() {
+            field.to_string()
+        } else {
+            format!("{field}.{}", self.name)
+        };
+
+        self.offset += offset / std::mem::size_of::<f32>();
+    }
+
+    fn same_shape(&self, other: &LayerShape) -> bool {
+        self.kind == other.kind && self.dims == other.dims
+    }
+}
+
+impl std::fmt::Display for LayerShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{:?}", self.kind, self.dims)
+    }
+}
+
+/// Reasons a saved network can be rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FormatError {
+    BadMagic,
+    UnsupportedVersion(u32),
+    ShapeMismatch {
+        expected: Vec<LayerShape>,
+        found: Vec<LayerShape>,
+    },
+    SizeMismatch {
+        expected: usize,
+        found: usize,
+    },
+    ChecksumMismatch,
+    Truncated,
+}
+
+impl std::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |shapes: &[LayerShape]| {
+            shapes
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        match self {
+            Self::BadMagic => write!(f, "not a goober network file"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported format version {v}"),
+            Self::ShapeMismatch { expected, found } => write!(
+                f,
+                "network shape mismatch: expected [{}], found [{}]",
+                list(expected),
+                list(found)
+            ),
+            Self::SizeMismatch { expected, found } => {
+                write!(f, "expected {expected} parameters, found {found}")
+            }
+            Self::ChecksumMismatch => write!(f, "checksum mismatch"),
+            Self::Truncated => write!(f, "unexpected end of data"),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<FormatError> for std::io::Error {
+    fn from(err: FormatError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Returns the shapes of every layer in `N`, in order.
+pub fn shapes_of<N: FeedForwardNetwork>() -> Vec<LayerShape> {
+    let mut shapes = Vec::new();
+    N::layer_shapes(&mut shapes);
+    shapes
+}
+
+pub(crate) fn params<N: FeedForwardNetwork>(net: &N) -> &[f32] {
+    let len = std::mem::size_of::<N>() / std::mem::size_of::<f32>();
+    unsafe { std::slice::from_raw_parts((net as *const N).cast(), len) }
+}
+
+pub(crate) fn params_mut<N: FeedForwardNetwork>(net: &mut N) -> &mut [f32] {
+    let len = std::mem::size_of::<N>() / std::mem::size_of::<f32>();
+    unsafe { std::slice::from_raw_parts_mut((net as *mut N).cast(), len) }
+}
+
+fn checksum(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+pub(crate) fn encode<N: FeedForwardNetwork>(net: &N) -> Vec<u8> {
+    let shapes = shapes_of::<N>();
+    let params = params(net);
+
+    let mut bytes = Vec::with_capacity(64 + 4 * params.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(shapes.len() as u32).to_le_bytes());
+
+    for shape in &shapes {
+        bytes.extend_from_slice(&(shape.kind.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(shape.kind.as_bytes());
+        bytes.extend_from_slice(&(shape.dims.len() as u32).to_le_bytes());
+        for &dim in &shape.dims {
+            bytes.extend_from_slice(&(dim as u64).to_le_bytes());
+        }
+    }
+
+    bytes.extend_from_slice(&(params.len() as u64).to_le_bytes());
+    for param in params {
+        bytes.extend_from_slice(&param.to_le_bytes());
+    }
+
+    bytes.extend_from_slice(&checksum(&bytes).to_le_bytes());
+    bytes
+}
+
+pub(crate) fn decode<N: FeedForwardNetwork>(bytes: &[u8]) -> Result<Box<N>, FormatError> {
+    let mut reader = Reader { bytes, pos: 0 };
+
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(FormatError::BadMagic);
+    }
+
+    let version = reader.u32()?;
+    if version != VERSION {
+        return Err(FormatError::UnsupportedVersion(version));
+    }
+
+    let num_shapes = reader.u32()?;
+    let mut found = Vec::new();
+    for _ in 0..num_shapes {
+        let len = reader.u32()? as usize;
+        let kind = String::from_utf8_lossy(reader.take(len)?).into_owned();
+        let num_dims = reader.u32()?;
+        let dims = (0..num_dims)
+            .map(|_| reader.u64().map(|d| d as usize))
+            .collect::<Result<_, _>>()?;
+        found.push(LayerShape {
+            kind,
+            dims,
+            name: String::new(),
+            offset: 0,
+            len: 0,
+        });
+    }
+
+    let expected = shapes_of::<N>();
+    let matches = found.len() == expected.len()
+        && found
+            .iter()
+            .zip(expected.iter())
+            .all(|(a, b)| a.same_shape(b));
+
+    if !matches {
+        return Err(FormatError::ShapeMismatch { expected, found });
+    }
+
+    let mut net = N::boxed_and_zeroed();
+    let params = params_mut(net.as_mut());
+
+    let num_params = reader.u64()? as usize;
+    if num_params != params.len() {
+        return Err(FormatError::SizeMismatch {
+            expected: params.len(),
+            found: num_params,
+        });
+    }
+
+    for param in params.iter_mut() {
+        *param = f32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+    }
+
+    let end = reader.pos;
+    if reader.u64()? != checksum(&bytes[..end]) {
+        return Err(FormatError::ChecksumMismatch);
+    }
+
+    Ok(net)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], FormatError> {
+        let slice = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or(FormatError::Truncated)?;
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, FormatError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, FormatError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+pub(crate) fn write_tag<W: std::io::Write>(w: &mut W, tag: &[u8; 4]) -> std::io::Result<()> {
+    w.write_all(tag)
+}
+
+pub(crate) fn read_tag<R: std::io::Read>(r: &mut R, tag: &[u8; 4]) -> std::io::Result<()> {
+    let mut found = [0; 4];
+    r.read_exact(&mut found)?;
+
+    if &found != tag {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "expected {:?}, found {:?}",
+                String::from_utf8_lossy(tag),
+                String::from_utf8_lossy(&found)
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn write_u64<W: std::io::Write>(w: &mut W, val: u64) -> std::io::Result<()> {
+    w.write_all(&val.to_le_bytes())
+}
+
+pub(crate) fn read_u64<R: std::io::Read>(r: &mut R) -> std::io::Result<u64> {
+    let mut bytes = [0; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub(crate) fn write_f32<W: std::io::Write>(w: &mut W, val: f32) -> std::io::Result<()> {
+    w.write_all(&val.to_le_bytes())
+}
+
+pub(crate) fn read_f32<R: std::io::Read>(r: &mut R) -> std::io::Result<f32> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+/// Writes a network prefixed by its length, so that it can be followed by more data.
+pub(crate) fn write_network<W: std::io::Write, N: FeedForwardNetwork>(
+    w: &mut W,
+    net: &N,
+) -> std::io::Result<()> {
+    let bytes = encode(net);
+    write_u64(w, bytes.len() as u64)?;
+    w.write_all(&bytes)
+}
+
+pub(crate) fn read_network<R: std::io::Read, N: FeedForwardNetwork>(
+    r: &mut R,
+) -> std::io::Result<Box<N>> {
+    let len = read_u64(r)?;
+    let mut bytes = Vec::new();
+    std::io::Read::read_to_end(&mut std::io::Read::take(r, len), &mut bytes)?;
+
+    if bytes.len() as u64 != len {
+        return Err(FormatError::Truncated.into());
+    }
+
+    Ok(decode(&bytes)?)
+}
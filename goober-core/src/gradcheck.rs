@@ -0,0 +1,91 @@
+//! Finite-difference checking of the gradients produced by `backprop`.
+
+use crate::{
+    format::{params, params_mut, shapes_of},
+    FeedForwardNetwork, Loss, OutputLayer,
+};
+
+/// Comparison of the numerical and analytical gradient of a single parameter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GradCheck {
+    /// Name of the layer containing the parameter.
+    pub layer: String,
+    /// Index of the parameter within its layer.
+    pub index: usize,
+    pub numerical: f32,
+    pub analytical: f32,
+    /// `|numerical - analytical|`, relative to their magnitudes if those exceed one.
+    pub error: f32,
+}
+
+impl std::fmt::Display for GradCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}[{}]: numerical {}, analytical {} (error {})",
+            self.layer, self.index, self.numerical, self.analytical, self.error
+        )
+    }
+}
+
+/// Perturbs each parameter of `net` by `±h` and compares the resulting change in
+/// `loss` to the gradient given by `backprop`, returning the worst parameter found.
+///
+/// The network is restored to its original parameters afterwards.
+pub fn gradcheck<N, L>(
+    net: &mut N,
+    input: &N::InputType,
+    target: &L::Target,
+    loss: &L,
+    h: f32,
+) -> Option<GradCheck>
+where
+    N: FeedForwardNetwork,
+    L: Loss<N::OutputType>,
+{
+    let eval = |net: &N| loss.loss(&net.out(input), target).0;
+
+    let mut grad = N::boxed_and_zeroed();
+    let layers = net.out_with_layers(input);
+    let (_, err) = loss.loss(&layers.output_layer(), target);
+    net.backprop(input, &mut grad, err, &layers);
+
+    let analytical = params(grad.as_ref());
+    let shapes = shapes_of::<N>();
+    let mut worst: Option<GradCheck> = None;
+
+    for (i, &analytical) in analytical.iter().enumerate() {
+        let orig = params(net)[i];
+
+        params_mut(net)[i] = orig + h;
+        let plus = eval(net);
+        params_mut(net)[i] = orig - h;
+        let minus = eval(net);
+        params_mut(net)[i] = orig;
+
+        let numerical = (plus - minus) / (2.0 * h);
+        let error = (numerical - analytical).abs() / (numerical.abs() + analytical.abs()).max(1.0);
+
+        if worst.as_ref().is_none_or(|w| error > w.error) {
+            let shape = shapes
+                .iter()
+                .find(|s| (s.offset..s.offset + s.len).contains(&i));
+
+            let (layer, index) = match shape {
+                Some(s) if s.name.is_empty() => (s.kind.clone(), i - s.offset),
+                Some(s) => (s.name.clone(), i - s.offset),
+                None => (String::from("?"), i),
+            };
+
+            worst = Some(GradCheck {
+                layer,
+                index,
+                numerical,
+                analytical,
+                error,
+            });
+        }
+    }
+
+    worst
+}
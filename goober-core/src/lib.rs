@@ -1,8 +1,25 @@
 pub mod activation;
+pub mod format;
+mod gradcheck;
+pub mod loss;
 mod matrix;
+pub mod optimizer;
+pub mod quantise;
+pub mod scheduler;
+mod simd;
+mod trainer;
 mod vector;
 
+use std::borrow::Borrow;
+
+pub use format::LayerShape;
+pub use gradcheck::{gradcheck, GradCheck};
+pub use loss::Loss;
 pub use matrix::Matrix;
+pub use optimizer::{LazyUpdate, Optimizer, OptimizerState, Step, TouchedRows};
+pub use quantise::{Quantise, QuantisedNetwork};
+pub use scheduler::LrScheduler;
+pub use trainer::Trainer;
 pub use vector::{SparseVector, Vector};
 
 pub trait OutputLayer<OutputType> {
@@ -14,7 +31,11 @@ pub trait FeedForwardNetwork: Sized {
     type OutputType: Clone;
     type Layers: OutputLayer<Self::OutputType>;
 
-    fn adam(&mut self, g: &Self, m: &mut Self, v: &mut Self, adj: f32, lr: f32);
+    /// Whether any parameter group is updated lazily, see [`Step::lazy`], so
+    /// training has to record the rows each batch touches.
+    const LAZY: bool = false;
+
+    fn update<O: Optimizer>(&mut self, g: &Self, m: &mut Self, v: &mut Self, opt: &O, step: &Step);
 
     fn boxed_and_zeroed() -> Box<Self> {
         unsafe {
@@ -27,17 +48,35 @@ pub trait FeedForwardNetwork: Sized {
         }
     }
 
-    fn write_to_bin(&self, path: &str) {
-        use std::io::Write;
+    /// Appends the shape of each layer, in order, to `shapes`.
+    fn layer_shapes(shapes: &mut Vec<LayerShape>);
 
-        let mut file = std::fs::File::create(path).unwrap();
+    fn save<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(&format::encode(self))
+    }
 
-        unsafe {
-            let ptr: *const Self = self;
-            let slice_ptr: *const u8 = std::mem::transmute(ptr);
-            let slice = std::slice::from_raw_parts(slice_ptr, std::mem::size_of_val(self));
-            file.write_all(slice).unwrap();
-        }
+    fn load<R: std::io::Read>(mut reader: R) -> std::io::Result<Box<Self>> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::load_from_bytes(&bytes)
+    }
+
+    fn write_to_bin(&self, path: &str) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        let mut writer = std::io::BufWriter::new(file);
+        self.save(&mut writer)?;
+        std::io::Write::flush(&mut writer)
+    }
+
+    fn read_from_bin(path: &str) -> std::io::Result<Box<Self>> {
+        let file = std::fs::File::open(path)?;
+        Self::load(std::io::BufReader::new(file))
+    }
+
+    /// Loads a network from bytes produced by `save`, e.g. from `include_bytes!`,
+    /// rejecting data saved from a network with different layer shapes.
+    fn load_from_bytes(bytes: &[u8]) -> std::io::Result<Box<Self>> {
+        Ok(format::decode(bytes)?)
     }
 
     fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers;
@@ -53,4 +92,41 @@ pub trait FeedForwardNetwork: Sized {
         out_err: Self::OutputType,
         layers: &Self::Layers,
     ) -> Self::InputType;
+
+    /// Records the rows that `input` activates in layers with sparse inputs,
+    /// which lazily updated layers restrict their update to, see [`Step::lazy`].
+    fn touched_rows(_: &Self::InputType, _: &mut TouchedRows) {}
+
+    /// Runs `out_with_layers` on every input of a batch.
+    fn out_with_layers_batch<I: Borrow<Self::InputType>>(&self, inputs: &[I]) -> Vec<Self::Layers> {
+        inputs
+            .iter()
+            .map(|input| self.out_with_layers(input.borrow()))
+            .collect()
+    }
+
+    /// Runs `out` on every input of a batch.
+    fn out_batch<I: Borrow<Self::InputType>>(&self, inputs: &[I]) -> Vec<Self::OutputType> {
+        self.out_with_layers_batch(inputs)
+            .iter()
+            .map(OutputLayer::output_layer)
+            .collect()
+    }
+
+    /// Runs `backprop` on every sample of a batch, in order, accumulating
+    /// into `grad` and returning the error of each input.
+    fn backprop_batch<I: Borrow<Self::InputType>, B: Borrow<Self::Layers>>(
+        &self,
+        inputs: &[I],
+        grad: &mut Self,
+        out_errs: Vec<Self::OutputType>,
+        layers: &[B],
+    ) -> Vec<Self::InputType> {
+        inputs
+            .iter()
+            .zip(out_errs)
+            .zip(layers)
+            .map(|((input, err), layers)| self.backprop(input.borrow(), grad, err, layers.borrow()))
+            .collect()
+    }
 }
@@ -0,0 +1,242 @@
+//! Loss functions, giving both the loss and its gradient with respect to
+//! the network output, to be passed as the error to `backprop`.
+
+use crate::Vector;
+
+pub trait Loss<Output> {
+    type Target;
+
+    /// Returns the loss and its gradient with respect to `output`.
+    fn loss(&self, output: &Output, target: &Self::Target) -> (f32, Output);
+}
+
+/// Mean Squared Error.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mse;
+
+impl<const N: usize> Loss<Vector<N>> for Mse {
+    type Target = Vector<N>;
+
+    fn loss(&self, output: &Vector<N>, target: &Vector<N>) -> (f32, Vector<N>) {
+        let mut loss = 0.0;
+        let grad = Vector::from_fn(|i| {
+            let diff = output[i] - target[i];
+            loss += diff * diff;
+            2.0 * diff / N as f32
+        });
+
+        (loss / N as f32, grad)
+    }
+}
+
+/// Target for [`SigmoidMse`]: an evaluation in the same units as the network
+/// output, and a game result (`0.0` loss, `0.5` draw, `1.0` win).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EvalWdl {
+    pub eval: f32,
+    pub wdl: f32,
+}
+
+/// Squared error between `sigmoid(output / scale)` and a blend of the
+/// game result and `sigmoid(eval / scale)`, weighted `blend` towards the result.
+#[derive(Clone, Copy, Debug)]
+pub struct SigmoidMse {
+    pub scale: f32,
+    pub blend: f32,
+}
+
+impl Loss<Vector<1>> for SigmoidMse {
+    type Target = EvalWdl;
+
+    fn loss(&self, output: &Vector<1>, target: &EvalWdl) -> (f32, Vector<1>) {
+        let expected =
+            self.blend * target.wdl + (1.0 - self.blend) * sigmoid(target.eval / self.scale);
+        let predicted = sigmoid(output[0] / self.scale);
+        let diff = predicted - expected;
+
+        let grad = 2.0 * diff * predicted * (1.0 - predicted) / self.scale;
+        (diff * diff, Vector::from_raw([grad]))
+    }
+}
+
+/// Binary Cross-Entropy, averaged over outputs.
+/// Takes logits, applying a sigmoid to each output internally,
+/// and a target probability for each output.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BinaryCrossEntropy;
+
+impl<const N: usize> Loss<Vector<N>> for BinaryCrossEntropy {
+    type Target = Vector<N>;
+
+    fn loss(&self, output: &Vector<N>, target: &Vector<N>) -> (f32, Vector<N>) {
+        let mut loss = 0.0;
+        let grad = Vector::from_fn(|i| {
+            let (x, t) = (output[i], target[i]);
+            loss += x.max(0.0) - x * t + (-x.abs()).exp().ln_1p();
+            (sigmoid(x) - t) / N as f32
+        });
+
+        (loss / N as f32, grad)
+    }
+}
+
+/// Cross-Entropy between `softmax(output)` and a target distribution.
+/// Takes logits, applying the softmax internally.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SoftmaxCrossEntropy;
+
+impl<const N: usize> Loss<Vector<N>> for SoftmaxCrossEntropy {
+    type Target = Vector<N>;
+
+    fn loss(&self, output: &Vector<N>, target: &Vector<N>) -> (f32, Vector<N>) {
+        let mut max = f32::NEG_INFINITY;
+        for i in 0..N {
+            max = max.max(output[i]);
+        }
+
+        let mut total = 0.0;
+        for i in 0..N {
+            total += (output[i] - max).exp();
+        }
+        let log_total = total.ln();
+
+        let mut loss = 0.0;
+        let mut target_total = 0.0;
+        for i in 0..N {
+            loss -= target[i] * (output[i] - max - log_total);
+            target_total += target[i];
+        }
+
+        let grad = Vector::from_fn(|i| {
+            let prob = (output[i] - max - log_total).exp();
+            prob * target_total - target[i]
+        });
+
+        (loss, grad)
+    }
+}
+
+/// Cross-Entropy between a predicted distribution, e.g. the output of a
+/// softmax layer, and a target distribution.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CrossEntropy;
+
+impl<const N: usize> Loss<Vector<N>> for CrossEntropy {
+    type Target = Vector<N>;
+
+    fn loss(&self, output: &Vector<N>, target: &Vector<N>) -> (f32, Vector<N>) {
+        const MIN_PROB: f32 = 1e-12;
+
+        let mut loss = 0.0;
+        let grad = Vector::from_fn(|i| {
+            let prob = output[i].max(MIN_PROB);
+            loss -= target[i] * prob.ln();
+            -target[i] / prob
+        });
+
+        (loss, grad)
+    }
+}
+
+/// Negative log-likelihood of a target distribution, given predicted
+/// log-probabilities, e.g. the output of a log-softmax layer.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NegativeLogLikelihood;
+
+impl<const N: usize> Loss<Vector<N>> for NegativeLogLikelihood {
+    type Target = Vector<N>;
+
+    fn loss(&self, output: &Vector<N>, target: &Vector<N>) -> (f32, Vector<N>) {
+        (-output.dot(target), -1.0 * *target)
+    }
+}
+
+/// Huber loss, averaged over outputs: quadratic for errors within `delta`, linear outside.
+#[derive(Clone, Copy, Debug)]
+pub struct Huber {
+    pub delta: f32,
+}
+
+impl<const N: usize> Loss<Vector<N>> for Huber {
+    type Target = Vector<N>;
+
+    fn loss(&self, output: &Vector<N>, target: &Vector<N>) -> (f32, Vector<N>) {
+        let mut loss = 0.0;
+        let grad = Vector::from_fn(|i| {
+            let diff = output[i] - target[i];
+
+            loss += if diff.abs() <= self.delta {
+                0.5 * diff * diff
+            } else {
+                self.delta * (diff.abs() - 0.5 * self.delta)
+            };
+
+            diff.clamp(-self.delta, self.delta) / N as f32
+        });
+
+        (loss / N as f32, grad)
+    }
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check<const N: usize, L: Loss<Vector<N>>>(loss: L, output: Vector<N>, target: L::Target) {
+        let (_, grad) = loss.loss(&output, &target);
+
+        for i in 0..N {
+            let h = 0.001;
+            let mut plus = output;
+            plus[i] += h;
+            let mut minus = output;
+            minus[i] -= h;
+
+            let numerical =
+                (loss.loss(&plus, &target).0 - loss.loss(&minus, &target).0) / (2.0 * h);
+            assert!(
+                (numerical - grad[i]).abs() < 1e-3,
+                "output {i}: numerical {numerical}, analytical {}",
+                grad[i]
+            );
+        }
+    }
+
+    #[test]
+    fn gradients() {
+        let output = Vector::from_raw([0.3, -1.2, 2.5]);
+        let target = Vector::from_raw([0.2, 0.5, 0.3]);
+
+        check(Mse, output, target);
+        check(BinaryCrossEntropy, output, target);
+        check(SoftmaxCrossEntropy, output, target);
+        check(Huber { delta: 1.0 }, output, target);
+        check(NegativeLogLikelihood, output, target);
+        check(CrossEntropy, Vector::from_raw([0.2, 0.7, 0.1]), target);
+
+        let sigmoid_mse = SigmoidMse {
+            scale: 400.0,
+            blend: 0.3,
+        };
+        let target = EvalWdl {
+            eval: 150.0,
+            wdl: 1.0,
+        };
+        check(sigmoid_mse, Vector::from_raw([-80.0]), target);
+    }
+
+    #[test]
+    fn values() {
+        let (loss, _) = Mse.loss(&Vector::from_raw([1.0, 3.0]), &Vector::from_raw([0.0, 1.0]));
+        assert_eq!(loss, 2.5);
+
+        let uniform = Vector::from_raw([0.25; 4]);
+        let (loss, grad) = SoftmaxCrossEntropy.loss(&Vector::zeroed(), &uniform);
+        assert!((loss - 4f32.ln()).abs() < 1e-6);
+        assert!(grad[0].abs() < 1e-6);
+    }
+}
@@ -1,4 +1,14 @@
-use crate::Vector;
+use std::borrow::Borrow;
+
+use crate::{
+    optimizer::{Optimizer, Step},
+    Vector,
+};
+
+/// Rows and samples handled together by the batched products, so that a block
+/// of rows stays in cache while it is applied to a block of samples.
+const BLOCK_ROWS: usize = 64;
+const BLOCK_SAMPLES: usize = 16;
 
 /// `N`x`M` Matrix Type.
 #[repr(C)]
@@ -59,18 +69,69 @@ impl<const M: usize, const N: usize> Matrix<M, N> {
     }
 
     pub fn transpose_mul(&self, out: &Vector<N>) -> Vector<M> {
-        Vector::from_fn(|i| {
-            let mut v = 0.0;
-            for j in 0..N {
-                v += self.inner[i][j] * out[j];
+        Vector::from_fn(|i| self.inner[i].dot(out))
+    }
+
+    /// Runs `mul` on every input of a batch, giving identical results.
+    pub fn mul_batch<I: Borrow<Vector<M>>>(&self, inputs: &[I]) -> Vec<Vector<N>> {
+        let mut results = vec![Vector::zeroed(); inputs.len()];
+
+        for (inputs, results) in inputs
+            .chunks(BLOCK_SAMPLES)
+            .zip(results.chunks_mut(BLOCK_SAMPLES))
+        {
+            for start in (0..M).step_by(BLOCK_ROWS) {
+                let rows = start..(start + BLOCK_ROWS).min(M);
+
+                for (input, result) in inputs.iter().zip(results.iter_mut()) {
+                    let input = input.borrow();
+                    for i in rows.clone() {
+                        result.madd(&self.inner[i], input[i]);
+                    }
+                }
             }
-            v
-        })
+        }
+
+        results
+    }
+
+    /// Runs `transpose_mul` on every output of a batch, giving identical results.
+    pub fn transpose_mul_batch(&self, outs: &[Vector<N>]) -> Vec<Vector<M>> {
+        outs.iter().map(|out| self.transpose_mul(out)).collect()
     }
 
-    pub fn adam(&mut self, g: &Self, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
+    /// Adds the outer product of `lhs[b]` and `rhs[b]` for each sample `b`, in order.
+    pub fn add_outer_batch<I: Borrow<Vector<M>>>(&mut self, lhs: &[I], rhs: &[Vector<N>]) {
+        assert_eq!(lhs.len(), rhs.len());
+
+        for start in (0..M).step_by(BLOCK_ROWS) {
+            let rows = start..(start + BLOCK_ROWS).min(M);
+
+            for (l, r) in lhs.iter().zip(rhs.iter()) {
+                let l = l.borrow();
+                for i in rows.clone() {
+                    self.inner[i].madd(r, l[i]);
+                }
+            }
+        }
+    }
+
+    pub fn update<O: Optimizer>(
+        &mut self,
+        g: &Self,
+        m: &mut Self,
+        v: &mut Self,
+        opt: &O,
+        step: &Step,
+    ) {
         for i in 0..M {
-            self.inner[i].adam(g.inner[i], &mut m.inner[i], &mut v.inner[i], adj, lr);
+            self.inner[i].update(&g.inner[i], &mut m.inner[i], &mut v.inner[i], opt, step);
+        }
+    }
+
+    pub fn clamp(&mut self, min: f32, max: f32) {
+        for row in self.inner.iter_mut() {
+            row.clamp(min, max);
         }
     }
 }
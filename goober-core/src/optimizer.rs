@@ -0,0 +1,536 @@
+//! Optimisers used to update network parameters from their gradients.
+
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+use crate::{
+    format::{read_network, read_tag, read_u64, write_network, write_tag, write_u64},
+    FeedForwardNetwork,
+};
+
+/// Values shared by every parameter updated in a single optimiser step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Step<'a> {
+    /// Multiplier applied to each gradient before use, e.g. `1 / batch_size`.
+    pub adj: f32,
+    /// Learning rate.
+    pub lr: f32,
+    /// Number of steps taken so far, including this one.
+    pub t: u32,
+    /// Overrides the optimiser's decoupled weight decay, if set.
+    pub weight_decay: Option<f32>,
+    /// Bounds that layers clamp their weights (not biases) to after updating, if set.
+    pub weight_clip: Option<(f32, f32)>,
+    /// Whether layers with sparse inputs only update the weights of the `touched`
+    /// rows, leaving the rest and their optimiser state untouched. Without
+    /// `touched` rows every weight is updated.
+    ///
+    /// Only [`LazyUpdate`] layers are affected. Each of their rows is bias
+    /// corrected with its own step count from `row_steps`, rather than `t`.
+    pub lazy: bool,
+    /// Rows activated by the batch in layers with sparse inputs, see
+    /// [`crate::FeedForwardNetwork::touched_rows`].
+    pub touched: Option<&'a TouchedRows>,
+    /// Number of steps each row has been touched in so far, including this one.
+    pub row_steps: &'a [u32],
+}
+
+impl<'a> Step<'a> {
+    pub const fn new(adj: f32, lr: f32, t: u32) -> Self {
+        Self {
+            adj,
+            lr,
+            t,
+            weight_decay: None,
+            weight_clip: None,
+            lazy: false,
+            touched: None,
+            row_steps: &[],
+        }
+    }
+
+    /// Step for a parameter group with its learning rate scaled by `lr_scale`.
+    pub fn with_lr_scale(mut self, lr_scale: f32) -> Self {
+        self.lr *= lr_scale;
+        self
+    }
+
+    /// Step for a parameter group with its own weight decay.
+    pub fn with_weight_decay(mut self, weight_decay: f32) -> Self {
+        self.weight_decay = Some(weight_decay);
+        self
+    }
+
+    /// Step for a parameter group whose weights are kept within `[min, max]`.
+    pub fn with_weight_clip(mut self, min: f32, max: f32) -> Self {
+        self.weight_clip = Some((min, max));
+        self
+    }
+
+    /// Step for a parameter group that is updated lazily, see [`Step::lazy`].
+    pub fn with_lazy(mut self) -> Self {
+        self.lazy = true;
+        self
+    }
+
+    /// Step for a batch that activated the given rows, see [`Step::touched`]
+    /// and [`Step::row_steps`].
+    pub fn with_touched(mut self, touched: &'a TouchedRows, row_steps: &'a [u32]) -> Self {
+        self.touched = Some(touched);
+        self.row_steps = row_steps;
+        self
+    }
+
+    /// Rows to update in a layer with sparse inputs, each with the step to update
+    /// it with, or `None` if all of them are updated with this step.
+    pub fn lazy_rows(&self) -> Option<impl Iterator<Item = (usize, Step<'a>)> + 'a> {
+        let step = *self;
+        let touched = self.touched.filter(|_| self.lazy)?;
+
+        Some(touched.iter().map(move |row| {
+            let t = step.row_steps[row];
+            (row, Step { t, ..step })
+        }))
+    }
+
+    /// Weight decay to apply, given the optimiser's default.
+    pub fn weight_decay_or(&self, default: f32) -> f32 {
+        self.weight_decay.unwrap_or(default)
+    }
+}
+
+/// Set of rows activated by a batch in layers with sparse inputs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TouchedRows {
+    bits: Vec<u64>,
+}
+
+impl TouchedRows {
+    pub fn insert(&mut self, row: usize) {
+        let word = row / 64;
+        if word >= self.bits.len() {
+            self.bits.resize(word + 1, 0);
+        }
+
+        self.bits[word] |= 1 << (row % 64);
+    }
+
+    pub fn contains(&self, row: usize) -> bool {
+        self.bits
+            .get(row / 64)
+            .is_some_and(|bits| bits & (1 << (row % 64)) != 0)
+    }
+
+    /// Every row in the set, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bits.iter().enumerate().flat_map(|(word, &bits)| {
+            let mut bits = bits;
+            std::iter::from_fn(move || {
+                (bits != 0).then(|| {
+                    let bit = bits.trailing_zeros() as usize;
+                    bits &= bits - 1;
+                    word * 64 + bit
+                })
+            })
+        })
+    }
+}
+
+impl std::ops::AddAssign<&TouchedRows> for TouchedRows {
+    fn add_assign(&mut self, rhs: &TouchedRows) {
+        if rhs.bits.len() > self.bits.len() {
+            self.bits.resize(rhs.bits.len(), 0);
+        }
+
+        for (bits, &rhs) in self.bits.iter_mut().zip(rhs.bits.iter()) {
+            *bits |= rhs;
+        }
+    }
+}
+
+/// Layers that honour [`Step::lazy`], the only ones `#[optimizer(lazy)]` can be given to.
+#[diagnostic::on_unimplemented(message = "`{Self}` cannot be updated lazily")]
+pub trait LazyUpdate: FeedForwardNetwork {}
+
+/// Updates a contiguous block of parameters from their gradients.
+///
+/// Every optimiser may keep up to two values of state for each parameter,
+/// `m` and `v`, which start zeroed and are kept between steps by the caller.
+pub trait Optimizer {
+    fn update(&self, p: &mut [f32], g: &[f32], m: &mut [f32], v: &mut [f32], step: &Step);
+}
+
+/// Optimiser state for a network: the two per-parameter moment buffers
+/// and the number of steps taken, used for bias correction.
+pub struct OptimizerState<N> {
+    momentum: Box<N>,
+    velocity: Box<N>,
+    steps: u32,
+    /// Number of steps each row of lazily updated layers has been touched in.
+    row_steps: Vec<u32>,
+}
+
+impl<N: FeedForwardNetwork> Default for OptimizerState<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: FeedForwardNetwork> OptimizerState<N> {
+    pub fn new() -> Self {
+        Self {
+            momentum: N::boxed_and_zeroed(),
+            velocity: N::boxed_and_zeroed(),
+            steps: 0,
+            row_steps: Vec::new(),
+        }
+    }
+
+    /// Number of steps taken so far.
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// Number of steps each row of lazily updated layers has been touched in.
+    pub fn row_steps(&self) -> &[u32] {
+        &self.row_steps
+    }
+
+    pub fn momentum(&self) -> &N {
+        &self.momentum
+    }
+
+    pub fn velocity(&self) -> &N {
+        &self.velocity
+    }
+
+    /// Updates `net` with gradient `grad`, scaled by `adj`.
+    ///
+    /// Lazily updated layers are restricted to the `touched` rows, which must
+    /// be given if the network has any, see [`FeedForwardNetwork::LAZY`].
+    pub fn step<O: Optimizer>(
+        &mut self,
+        net: &mut N,
+        grad: &N,
+        opt: &O,
+        adj: f32,
+        lr: f32,
+        touched: Option<&TouchedRows>,
+    ) {
+        debug_assert!(
+            touched.is_some() || !N::LAZY,
+            "lazily updated networks need the touched rows"
+        );
+
+        self.steps += 1;
+        let mut step = Step::new(adj, lr, self.steps);
+
+        if let Some(touched) = touched {
+            for row in touched.iter() {
+                if row >= self.row_steps.len() {
+                    self.row_steps.resize(row + 1, 0);
+                }
+                self.row_steps[row] += 1;
+            }
+
+            step = step.with_touched(touched, &self.row_steps);
+        }
+
+        net.update(grad, &mut self.momentum, &mut self.velocity, opt, &step);
+    }
+
+    pub fn save<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_tag(writer, b"OPTS")?;
+        write_u64(writer, u64::from(self.steps))?;
+        write_u64(writer, self.row_steps.len() as u64)?;
+        for &steps in &self.row_steps {
+            write_u64(writer, u64::from(steps))?;
+        }
+        write_network(writer, self.momentum.as_ref())?;
+        write_network(writer, self.velocity.as_ref())
+    }
+
+    pub fn load<R: Read>(reader: &mut R) -> Result<Self> {
+        read_tag(reader, b"OPTS")?;
+        let steps = read_steps(reader)?;
+        let rows = read_u64(reader)?;
+        let row_steps = (0..rows)
+            .map(|_| read_steps(reader))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            momentum: read_network(reader)?,
+            velocity: read_network(reader)?,
+            steps,
+            row_steps,
+        })
+    }
+}
+
+fn read_steps<R: Read>(reader: &mut R) -> Result<u32> {
+    u32::try_from(read_u64(reader)?).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidData,
+            "optimizer step count does not fit in a u32",
+        )
+    })
+}
+
+/// Stochastic Gradient Descent, with optional (Nesterov) momentum
+/// and decoupled weight decay.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sgd {
+    pub momentum: f32,
+    pub nesterov: bool,
+    pub weight_decay: f32,
+}
+
+impl Default for Sgd {
+    fn default() -> Self {
+        Self {
+            momentum: 0.0,
+            nesterov: false,
+            weight_decay: 0.0,
+        }
+    }
+}
+
+impl Sgd {
+    pub fn with_momentum(momentum: f32) -> Self {
+        Self {
+            momentum,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_nesterov(momentum: f32) -> Self {
+        Self {
+            momentum,
+            nesterov: true,
+            ..Default::default()
+        }
+    }
+}
+
+impl Optimizer for Sgd {
+    fn update(&self, p: &mut [f32], g: &[f32], m: &mut [f32], _: &mut [f32], step: &Step) {
+        let weight_decay = step.weight_decay_or(self.weight_decay);
+
+        for ((p, &g), m) in p.iter_mut().zip(g.iter()).zip(m.iter_mut()) {
+            let g = step.adj * g;
+            *m = self.momentum * *m + g;
+
+            *p -= step.lr * weight_decay * *p;
+
+            let dir = if self.nesterov {
+                g + self.momentum * *m
+            } else {
+                *m
+            };
+
+            *p -= step.lr * dir;
+        }
+    }
+}
+
+/// Adam, with bias correction.
+///
+/// Applies no weight decay unless a parameter group sets one, see [`AdamW`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adam {
+    pub b1: f32,
+    pub b2: f32,
+    pub eps: f32,
+}
+
+impl Default for Adam {
+    fn default() -> Self {
+        Self {
+            b1: 0.9,
+            b2: 0.999,
+            eps: 0.000_000_01,
+        }
+    }
+}
+
+impl Optimizer for Adam {
+    fn update(&self, p: &mut [f32], g: &[f32], m: &mut [f32], v: &mut [f32], step: &Step) {
+        adam(self, step.weight_decay_or(0.0), p, g, m, v, step);
+    }
+}
+
+/// Adam with decoupled weight decay.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdamW {
+    pub adam: Adam,
+    pub weight_decay: f32,
+}
+
+impl Default for AdamW {
+    fn default() -> Self {
+        Self {
+            adam: Adam::default(),
+            weight_decay: 0.01,
+        }
+    }
+}
+
+impl Optimizer for AdamW {
+    fn update(&self, p: &mut [f32], g: &[f32], m: &mut [f32], v: &mut [f32], step: &Step) {
+        adam(
+            &self.adam,
+            step.weight_decay_or(self.weight_decay),
+            p,
+            g,
+            m,
+            v,
+            step,
+        );
+    }
+}
+
+fn adam(
+    cfg: &Adam,
+    weight_decay: f32,
+    p: &mut [f32],
+    g: &[f32],
+    m: &mut [f32],
+    v: &mut [f32],
+    step: &Step,
+) {
+    let t = step.t.max(1) as i32;
+    let m_corr = 1.0 / (1.0 - cfg.b1.powi(t));
+    let v_corr = 1.0 / (1.0 - cfg.b2.powi(t));
+
+    for (((p, &g), m), v) in p
+        .iter_mut()
+        .zip(g.iter())
+        .zip(m.iter_mut())
+        .zip(v.iter_mut())
+    {
+        let g = step.adj * g;
+        *m = cfg.b1 * *m + (1. - cfg.b1) * g;
+        *v = cfg.b2 * *v + (1. - cfg.b2) * g * g;
+
+        *p -= step.lr * weight_decay * *p;
+        *p -= step.lr * m_corr * *m / ((v_corr * *v).sqrt() + cfg.eps);
+    }
+}
+
+/// Lion (EvoLved Sign Momentum), with decoupled weight decay.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lion {
+    pub b1: f32,
+    pub b2: f32,
+    pub weight_decay: f32,
+}
+
+impl Default for Lion {
+    fn default() -> Self {
+        Self {
+            b1: 0.9,
+            b2: 0.99,
+            weight_decay: 0.0,
+        }
+    }
+}
+
+impl Optimizer for Lion {
+    fn update(&self, p: &mut [f32], g: &[f32], m: &mut [f32], _: &mut [f32], step: &Step) {
+        let weight_decay = step.weight_decay_or(self.weight_decay);
+
+        for ((p, &g), m) in p.iter_mut().zip(g.iter()).zip(m.iter_mut()) {
+            let g = step.adj * g;
+            let c = self.b1 * *m + (1. - self.b1) * g;
+
+            let sign = if c > 0.0 {
+                1.0
+            } else if c < 0.0 {
+                -1.0
+            } else {
+                0.0
+            };
+
+            *p -= step.lr * (sign + weight_decay * *p);
+            *m = self.b2 * *m + (1. - self.b2) * g;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn minimise<O: Optimizer>(opt: O, lr: f32) -> f32 {
+        // f(p) = (p - 3)^2, df/dp = 2(p - 3)
+        let mut p = [0.0];
+        let mut m = [0.0];
+        let mut v = [0.0];
+
+        for t in 1..=2000 {
+            let g = [2.0 * (p[0] - 3.0)];
+            opt.update(&mut p, &g, &mut m, &mut v, &Step::new(1.0, lr, t));
+        }
+
+        p[0]
+    }
+
+    #[test]
+    fn optimisers_converge() {
+        assert!((minimise(Sgd::default(), 0.01) - 3.0).abs() < 0.01);
+        assert!((minimise(Sgd::with_momentum(0.9), 0.01) - 3.0).abs() < 0.01);
+        assert!((minimise(Sgd::with_nesterov(0.9), 0.01) - 3.0).abs() < 0.01);
+        assert!((minimise(Adam::default(), 0.01) - 3.0).abs() < 0.01);
+        assert!((minimise(Lion::default(), 0.003) - 3.0).abs() < 0.01);
+
+        let adamw = AdamW {
+            weight_decay: 0.0,
+            ..Default::default()
+        };
+        assert!((minimise(adamw, 0.01) - 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn adamw_decays() {
+        let adamw = AdamW::default();
+        let mut p = [1.0];
+        adamw.update(
+            &mut p,
+            &[0.0],
+            &mut [0.0],
+            &mut [0.0],
+            &Step::new(1.0, 0.1, 1),
+        );
+        assert!((p[0] - 0.999).abs() < 1e-6);
+    }
+
+    #[test]
+    fn group_weight_decay() {
+        let step = Step::new(1.0, 0.1, 1).with_weight_decay(0.5);
+
+        for opt in [
+            &Sgd::default() as &dyn Optimizer,
+            &Adam::default(),
+            &Lion::default(),
+        ] {
+            let mut p = [1.0];
+            opt.update(&mut p, &[0.0], &mut [0.0], &mut [0.0], &step);
+            assert!((p[0] - 0.95).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn touched_rows() {
+        let mut a = TouchedRows::default();
+        a.insert(70);
+        a.insert(3);
+        a.insert(3);
+
+        let mut b = TouchedRows::default();
+        b.insert(200);
+        b.insert(63);
+        a += &b;
+
+        assert_eq!(a.iter().collect::<Vec<_>>(), [3, 63, 70, 200]);
+        assert!(a.contains(200) && !a.contains(64) && !a.contains(1000));
+    }
+}
@@ -0,0 +1,402 @@
+//! Post-training quantisation of networks to integer parameters, which are
+//! then evaluated with integer arithmetic.
+//!
+//! Every quantised value `x` represents the real value `x / scale`. Each layer
+//! quantises its weights with its own scale, so its sums have the scale
+//! `input_scale * weight_scale` and are rescaled to the layer's output scale
+//! after activation.
+
+use crate::{
+    activation::{Activation, CReLU, Identity, ReLU, SCReLU},
+    FeedForwardNetwork, Vector,
+};
+
+/// How quantised values are rounded to integers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rounding {
+    /// Round to nearest, with halves rounded up.
+    #[default]
+    Nearest,
+    Floor,
+    Truncate,
+}
+
+impl Rounding {
+    pub fn round(self, x: f64) -> f64 {
+        match self {
+            Self::Nearest => (x + 0.5).floor(),
+            Self::Floor => x.floor(),
+            Self::Truncate => x.trunc(),
+        }
+    }
+
+    /// Divides `num` by the positive `den`.
+    pub fn div(self, num: i128, den: i128) -> i128 {
+        match self {
+            Self::Nearest => (2 * num + den).div_euclid(2 * den),
+            Self::Floor => num.div_euclid(den),
+            Self::Truncate => num / den,
+        }
+    }
+}
+
+/// Integer type that quantised parameters are stored as.
+pub trait Integer: Copy + Default + Into<i64> {
+    const MIN: i64;
+    const MAX: i64;
+
+    /// Converts `x`, which must be in `MIN..=MAX`.
+    fn from_i64(x: i64) -> Self;
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {$(
+        impl Integer for $t {
+            const MIN: i64 = <$t>::MIN as i64;
+            const MAX: i64 = <$t>::MAX as i64;
+
+            fn from_i64(x: i64) -> Self {
+                x as $t
+            }
+        }
+    )*};
+}
+
+impl_integer!(i8, i16, i32);
+
+/// Options for converting parameters to integers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quantiser {
+    pub rounding: Rounding,
+    /// Clamps parameters that do not fit their integer type, instead of failing.
+    pub saturate: bool,
+}
+
+impl Quantiser {
+    /// Quantises `x` at the given scale.
+    pub fn quantise<I: Integer>(&self, x: f32, scale: i32) -> Result<I, QuantiseError> {
+        let q = self.rounding.round(f64::from(x) * f64::from(scale));
+
+        if !self.saturate && (q < I::MIN as f64 || q > I::MAX as f64) {
+            return Err(QuantiseError::Saturated {
+                layer: String::new(),
+                value: x,
+                scale,
+            });
+        }
+
+        Ok(I::from_i64((q as i64).clamp(I::MIN, I::MAX)))
+    }
+
+    /// Quantises `x` at the given scale, clamping it if it does not fit.
+    pub fn saturating<I: Integer>(&self, x: f32, scale: i32) -> I {
+        let q = self.rounding.round(f64::from(x) * f64::from(scale));
+        I::from_i64((q as i64).clamp(I::MIN, I::MAX))
+    }
+
+    /// Rounds `x` to the nearest value representable by `I` at the given scale,
+    /// such that quantising the result again gives the same integer.
+    pub fn fake_quantise<I: Integer>(&self, x: f32, scale: i32) -> f32 {
+        let q: i64 = self.saturating::<I>(x, scale).into();
+        let y = (q as f64 / f64::from(scale)) as f32;
+
+        // `y` may be just below or above `q / scale`, where flooring or truncating it misses `q`
+        match self.saturating::<I>(y, scale).into().cmp(&q) {
+            std::cmp::Ordering::Less => y.next_up(),
+            std::cmp::Ordering::Equal => y,
+            std::cmp::Ordering::Greater => y.next_down(),
+        }
+    }
+}
+
+/// Scales given to a single layer, e.g. by `#[quantise(weights = .., output = ..)]`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Scales {
+    /// Scale of the layer's weights, required by every layer with parameters.
+    pub weights: Option<i32>,
+    /// Scale of the layer's output, defaults to the scale of its sums.
+    pub output: Option<i32>,
+}
+
+impl Scales {
+    pub fn weights(&self) -> Result<i32, QuantiseError> {
+        self.weights.ok_or(QuantiseError::MissingScale {
+            layer: String::new(),
+        })
+    }
+
+    /// Scale of the sums of a layer with quantised inputs, `input_scale * weights`.
+    pub fn sum(&self, input_scale: i32) -> Result<i32, QuantiseError> {
+        let weight_scale = self.weights()?;
+        input_scale
+            .checked_mul(weight_scale)
+            .ok_or(QuantiseError::ScaleOverflow {
+                layer: String::new(),
+                input_scale,
+                weight_scale,
+            })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum QuantiseError {
+    /// A parameter does not fit its integer type at the given scale.
+    Saturated {
+        layer: String,
+        value: f32,
+        scale: i32,
+    },
+    /// A layer was not given a weight scale.
+    MissingScale { layer: String },
+    /// The scale of a layer's sums does not fit in an `i32`.
+    ScaleOverflow {
+        layer: String,
+        input_scale: i32,
+        weight_scale: i32,
+    },
+}
+
+impl QuantiseError {
+    /// Prefixes the name of the layer with the field containing it.
+    pub fn nest(mut self, field: &str) -> Self {
+        let (Self::Saturated { layer, .. }
+        | Self::MissingScale { layer }
+        | Self::ScaleOverflow { layer, .. }) = &mut self;
+
+        *layer = if layer.is_empty() {
+            field.to_string()
+        } else {
+            format!("{field}.{layer}")
+        };
+
+        self
+    }
+}
+
+impl std::fmt::Display for QuantiseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Saturated {
+                layer,
+                value,
+                scale,
+            } => write!(
+                f,
+                "{layer}: parameter {value} does not fit its integer type at scale {scale}"
+            ),
+            Self::MissingScale { layer } => write!(f, "{layer}: no weight scale given"),
+            Self::ScaleOverflow {
+                layer,
+                input_scale,
+                weight_scale,
+            } => write!(
+                f,
+                "{layer}: input scale {input_scale} times weight scale {weight_scale} overflows i32"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for QuantiseError {}
+
+/// Activation function that can be evaluated on quantised values.
+pub trait QuantisedActivation: Activation {
+    /// Power of the input scale that the output of `activate_int` has.
+    const POWER: u32;
+
+    /// Activates `x`, quantised at `scale`.
+    fn activate_int(x: i64, scale: i64) -> i64;
+
+    /// Activates `x`, quantised at `scale`, and rescales the result to `output_scale`.
+    fn requantise(x: i64, scale: i64, output_scale: i64, rounding: Rounding) -> i32 {
+        let out = rounding.div(
+            i128::from(Self::activate_int(x, scale)) * i128::from(output_scale),
+            i128::from(scale).pow(Self::POWER),
+        );
+        out.clamp(i128::from(i32::MIN), i128::from(i32::MAX)) as i32
+    }
+}
+
+impl QuantisedActivation for Identity {
+    const POWER: u32 = 1;
+
+    fn activate_int(x: i64, _: i64) -> i64 {
+        x
+    }
+}
+
+impl QuantisedActivation for ReLU {
+    const POWER: u32 = 1;
+
+    fn activate_int(x: i64, _: i64) -> i64 {
+        x.max(0)
+    }
+}
+
+impl QuantisedActivation for CReLU {
+    const POWER: u32 = 1;
+
+    fn activate_int(x: i64, scale: i64) -> i64 {
+        x.clamp(0, scale)
+    }
+}
+
+impl QuantisedActivation for SCReLU {
+    const POWER: u32 = 2;
+
+    fn activate_int(x: i64, scale: i64) -> i64 {
+        let clamped = x.clamp(0, scale);
+        clamped * clamped
+    }
+}
+
+/// Network with integer parameters, evaluated with integer arithmetic.
+pub trait QuantisedNetwork {
+    type InputType;
+    type OutputType;
+
+    fn out(&self, input: &Self::InputType) -> Self::OutputType;
+
+    /// Scale that the output is quantised at.
+    fn output_scale(&self) -> i32;
+}
+
+/// Network that can be converted to a [`QuantisedNetwork`].
+pub trait Quantise: FeedForwardNetwork {
+    type Quantised: QuantisedNetwork;
+
+    /// Quantises the network, given the scale its input is quantised at.
+    fn quantise(
+        &self,
+        q: &Quantiser,
+        input_scale: i32,
+        scales: Scales,
+    ) -> Result<Self::Quantised, QuantiseError>;
+
+    /// Forward pass that rounds parameters and outputs to the values the quantised
+    /// network would use, returning the layers and the scale of the output.
+    ///
+    /// Passing the resulting layers to `backprop` treats the rounding as the
+    /// identity (a straight-through estimator), so the floating-point parameters
+    /// are trained to match the integer network's output.
+    fn out_with_layers_fake_quantised(
+        &self,
+        input: &Self::InputType,
+        q: &Quantiser,
+        input_scale: i32,
+        scales: Scales,
+    ) -> Result<(Self::Layers, i32), QuantiseError>;
+
+    /// Quantises an input to the network, saturating values that do not fit.
+    fn quantise_input(
+        q: &Quantiser,
+        input: &Self::InputType,
+        input_scale: i32,
+    ) -> <Self::Quantised as QuantisedNetwork>::InputType;
+}
+
+/// Error of a quantised network's outputs against the original network.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct QuantisationReport {
+    pub max_error: f32,
+    pub mean_error: f32,
+    /// Number of output values compared.
+    pub outputs: usize,
+}
+
+impl std::fmt::Display for QuantisationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "max error {}, mean error {} over {} outputs",
+            self.max_error, self.mean_error, self.outputs
+        )
+    }
+}
+
+/// Compares the outputs of `quantised`, dequantised, with those of `net` over `inputs`.
+pub fn quantisation_error<'a, N, const K: usize>(
+    net: &N,
+    quantised: &N::Quantised,
+    q: &Quantiser,
+    input_scale: i32,
+    inputs: impl IntoIterator<Item = &'a N::InputType>,
+) -> QuantisationReport
+where
+    N: Quantise<OutputType = Vector<K>> + 'a,
+    N::Quantised: QuantisedNetwork<OutputType = [i32; K]>,
+{
+    let scale = quantised.output_scale() as f32;
+    let mut report = QuantisationReport::default();
+    let mut total = 0.0;
+
+    for input in inputs {
+        let expected = net.out(input);
+        let actual = quantised.out(&N::quantise_input(q, input, input_scale));
+
+        for (i, &actual) in actual.iter().enumerate() {
+            let error = (expected[i] - actual as f32 / scale).abs();
+            report.max_error = report.max_error.max(error);
+            total += error;
+            report.outputs += 1;
+        }
+    }
+
+    if report.outputs > 0 {
+        report.mean_error = total / report.outputs as f32;
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rounding() {
+        assert_eq!(Rounding::Nearest.div(5, 2), 3);
+        assert_eq!(Rounding::Nearest.div(-5, 2), -2);
+        assert_eq!(Rounding::Floor.div(-5, 2), -3);
+        assert_eq!(Rounding::Truncate.div(-5, 2), -2);
+        assert_eq!(Rounding::Nearest.round(-1.5), -1.0);
+    }
+
+    #[test]
+    fn saturation() {
+        let q = Quantiser::default();
+        assert_eq!(q.quantise::<i8>(0.5, 64), Ok(32));
+        assert!(q.quantise::<i8>(2.0, 64).is_err());
+
+        let q = Quantiser {
+            saturate: true,
+            ..Default::default()
+        };
+        assert_eq!(q.quantise::<i8>(2.0, 64), Ok(127));
+        assert_eq!(q.quantise::<i8>(-3.0, 64), Ok(-128));
+    }
+
+    #[test]
+    fn fake_quantise_round_trips() {
+        for rounding in [Rounding::Nearest, Rounding::Floor, Rounding::Truncate] {
+            let q = Quantiser {
+                rounding,
+                saturate: false,
+            };
+
+            for k in -1000..1000 {
+                let x = k as f32 / 255.0;
+                let fake = q.fake_quantise::<i16>(x, 255);
+                assert_eq!(q.saturating::<i16>(fake, 255), q.saturating::<i16>(x, 255));
+            }
+        }
+    }
+
+    #[test]
+    fn activations() {
+        // 0.5 at scale 256, squared and rescaled to 255
+        assert_eq!(SCReLU::requantise(128, 256, 255, Rounding::Nearest), 64);
+        assert_eq!(CReLU::requantise(300, 256, 255, Rounding::Nearest), 255);
+        assert_eq!(ReLU::requantise(-7, 256, 255, Rounding::Nearest), 0);
+    }
+}
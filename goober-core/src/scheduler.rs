@@ -0,0 +1,284 @@
+//! Learning rate schedules, queried by step (a batch or an epoch, as chosen by the trainer).
+
+use std::io::{Read, Result, Write};
+
+use crate::format::{read_f32, read_tag, read_u64, write_f32, write_tag, write_u64};
+
+/// Learning rate as a function of the number of steps taken.
+///
+/// Schedules are pure functions of `step`, so saving one alongside the step
+/// count is enough to resume a run on the same schedule.
+pub trait LrScheduler {
+    fn lr(&self, step: usize) -> f32;
+
+    fn save<W: Write>(&self, writer: &mut W) -> Result<()>;
+
+    fn load<R: Read>(reader: &mut R) -> Result<Self>
+    where
+        Self: Sized;
+}
+
+/// Fixed learning rate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Constant {
+    pub lr: f32,
+}
+
+impl LrScheduler for Constant {
+    fn lr(&self, _: usize) -> f32 {
+        self.lr
+    }
+
+    fn save<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_tag(writer, b"CNST")?;
+        write_f32(writer, self.lr)
+    }
+
+    fn load<R: Read>(reader: &mut R) -> Result<Self> {
+        read_tag(reader, b"CNST")?;
+        Ok(Self {
+            lr: read_f32(reader)?,
+        })
+    }
+}
+
+/// Multiplies the learning rate by `gamma` every `step_size` steps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StepDecay {
+    pub lr: f32,
+    pub gamma: f32,
+    pub step_size: usize,
+}
+
+impl LrScheduler for StepDecay {
+    fn lr(&self, step: usize) -> f32 {
+        self.lr * self.gamma.powi((step / self.step_size.max(1)) as i32)
+    }
+
+    fn save<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_tag(writer, b"STEP")?;
+        write_f32(writer, self.lr)?;
+        write_f32(writer, self.gamma)?;
+        write_u64(writer, self.step_size as u64)
+    }
+
+    fn load<R: Read>(reader: &mut R) -> Result<Self> {
+        read_tag(reader, b"STEP")?;
+        Ok(Self {
+            lr: read_f32(reader)?,
+            gamma: read_f32(reader)?,
+            step_size: read_u64(reader)? as usize,
+        })
+    }
+}
+
+/// Multiplies the learning rate by `gamma` every step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Exponential {
+    pub lr: f32,
+    pub gamma: f32,
+}
+
+impl LrScheduler for Exponential {
+    fn lr(&self, step: usize) -> f32 {
+        self.lr * self.gamma.powf(step as f32)
+    }
+
+    fn save<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_tag(writer, b"EXPO")?;
+        write_f32(writer, self.lr)?;
+        write_f32(writer, self.gamma)
+    }
+
+    fn load<R: Read>(reader: &mut R) -> Result<Self> {
+        read_tag(reader, b"EXPO")?;
+        Ok(Self {
+            lr: read_f32(reader)?,
+            gamma: read_f32(reader)?,
+        })
+    }
+}
+
+/// Anneals from `max_lr` to `min_lr` along a half cosine over `steps` steps,
+/// then stays at `min_lr`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cosine {
+    pub max_lr: f32,
+    pub min_lr: f32,
+    pub steps: usize,
+}
+
+impl LrScheduler for Cosine {
+    fn lr(&self, step: usize) -> f32 {
+        cosine(self.max_lr, self.min_lr, step.min(self.steps), self.steps)
+    }
+
+    fn save<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_tag(writer, b"COSN")?;
+        write_f32(writer, self.max_lr)?;
+        write_f32(writer, self.min_lr)?;
+        write_u64(writer, self.steps as u64)
+    }
+
+    fn load<R: Read>(reader: &mut R) -> Result<Self> {
+        read_tag(reader, b"COSN")?;
+        Ok(Self {
+            max_lr: read_f32(reader)?,
+            min_lr: read_f32(reader)?,
+            steps: read_u64(reader)? as usize,
+        })
+    }
+}
+
+/// Cosine annealing with warm restarts: the first cycle lasts `period` steps
+/// and each subsequent cycle is `mult` times longer than the last.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WarmRestarts {
+    pub max_lr: f32,
+    pub min_lr: f32,
+    pub period: usize,
+    pub mult: usize,
+}
+
+impl LrScheduler for WarmRestarts {
+    fn lr(&self, mut step: usize) -> f32 {
+        let mut period = self.period.max(1);
+
+        while step >= period {
+            step -= period;
+            period *= self.mult.max(1);
+        }
+
+        cosine(self.max_lr, self.min_lr, step, period)
+    }
+
+    fn save<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_tag(writer, b"SGDR")?;
+        write_f32(writer, self.max_lr)?;
+        write_f32(writer, self.min_lr)?;
+        write_u64(writer, self.period as u64)?;
+        write_u64(writer, self.mult as u64)
+    }
+
+    fn load<R: Read>(reader: &mut R) -> Result<Self> {
+        read_tag(reader, b"SGDR")?;
+        Ok(Self {
+            max_lr: read_f32(reader)?,
+            min_lr: read_f32(reader)?,
+            period: read_u64(reader)? as usize,
+            mult: read_u64(reader)? as usize,
+        })
+    }
+}
+
+/// Linearly ramps up to the initial learning rate of `inner` over `steps` steps,
+/// after which `inner` runs as if starting from step zero.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Warmup<S> {
+    pub steps: usize,
+    pub inner: S,
+}
+
+impl<S: LrScheduler> LrScheduler for Warmup<S> {
+    fn lr(&self, step: usize) -> f32 {
+        if step < self.steps {
+            self.inner.lr(0) * (step + 1) as f32 / self.steps as f32
+        } else {
+            self.inner.lr(step - self.steps)
+        }
+    }
+
+    fn save<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_tag(writer, b"WARM")?;
+        write_u64(writer, self.steps as u64)?;
+        self.inner.save(writer)
+    }
+
+    fn load<R: Read>(reader: &mut R) -> Result<Self> {
+        read_tag(reader, b"WARM")?;
+        Ok(Self {
+            steps: read_u64(reader)? as usize,
+            inner: S::load(reader)?,
+        })
+    }
+}
+
+fn cosine(max_lr: f32, min_lr: f32, step: usize, steps: usize) -> f32 {
+    let progress = step as f32 / steps.max(1) as f32;
+    min_lr + 0.5 * (max_lr - min_lr) * (1.0 + (std::f32::consts::PI * progress).cos())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn schedules() {
+        let step = StepDecay {
+            lr: 0.1,
+            gamma: 0.5,
+            step_size: 10,
+        };
+        assert!(close(step.lr(9), 0.1));
+        assert!(close(step.lr(10), 0.05));
+        assert!(close(step.lr(25), 0.025));
+
+        let exp = Exponential {
+            lr: 1.0,
+            gamma: 0.9,
+        };
+        assert!(close(exp.lr(2), 0.81));
+
+        let cos = Cosine {
+            max_lr: 1.0,
+            min_lr: 0.0,
+            steps: 100,
+        };
+        assert!(close(cos.lr(0), 1.0));
+        assert!(close(cos.lr(50), 0.5));
+        assert!(close(cos.lr(100), 0.0));
+        assert!(close(cos.lr(1000), 0.0));
+
+        let sgdr = WarmRestarts {
+            max_lr: 1.0,
+            min_lr: 0.0,
+            period: 10,
+            mult: 2,
+        };
+        assert!(close(sgdr.lr(10), 1.0));
+        assert!(close(sgdr.lr(20), 0.5));
+        assert!(close(sgdr.lr(30), 1.0));
+
+        let warmup = Warmup {
+            steps: 4,
+            inner: Constant { lr: 0.4 },
+        };
+        assert!(close(warmup.lr(0), 0.1));
+        assert!(close(warmup.lr(3), 0.4));
+        assert!(close(warmup.lr(100), 0.4));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let sched = Warmup {
+            steps: 100,
+            inner: WarmRestarts {
+                max_lr: 0.001,
+                min_lr: 0.0001,
+                period: 1000,
+                mult: 2,
+            },
+        };
+
+        let mut buf = Vec::new();
+        sched.save(&mut buf).unwrap();
+
+        let loaded = Warmup::<WarmRestarts>::load(&mut buf.as_slice()).unwrap();
+        assert_eq!(sched, loaded);
+        assert!(Warmup::<Cosine>::load(&mut buf.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//! Kernels behind `Vector` and `Matrix` arithmetic, with explicit SIMD
+//! implementations selected at runtime and a scalar fallback.
+//!
+//! Every implementation gives bit-for-bit identical results: sums are kept in
+//! `LANES` partial sums, where element `i` is added to partial sum `i % LANES`
+//! in order, and the partial sums are then reduced in a fixed order.
+
+use std::sync::OnceLock;
+
+const LANES: usize = 16;
+
+/// Instruction set used by the kernels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Level {
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Sse2,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    #[cfg(target_arch = "x86_64")]
+    Avx512,
+}
+
+impl Level {
+    /// Best level supported by the running CPU.
+    pub(crate) fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx512f") {
+                return Self::Avx512;
+            }
+            if is_x86_feature_detected!("avx2") {
+                return Self::Avx2;
+            }
+            if is_x86_feature_detected!("sse2") {
+                return Self::Sse2;
+            }
+        }
+
+        Self::Scalar
+    }
+
+    /// Every level supported by the running CPU.
+    #[cfg(test)]
+    fn supported() -> Vec<Self> {
+        let mut levels = vec![Self::Scalar];
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("sse2") {
+                levels.push(Self::Sse2);
+            }
+            if is_x86_feature_detected!("avx2") {
+                levels.push(Self::Avx2);
+            }
+            if is_x86_feature_detected!("avx512f") {
+                levels.push(Self::Avx512);
+            }
+        }
+
+        levels
+    }
+}
+
+fn level() -> Level {
+    static LEVEL: OnceLock<Level> = OnceLock::new();
+    *LEVEL.get_or_init(Level::detect)
+}
+
+/// Returns `a . b`.
+pub(crate) fn dot(a: &[f32], b: &[f32]) -> f32 {
+    dot_with(level(), a, b)
+}
+
+/// Performs `a += mul * b`.
+pub(crate) fn madd(a: &mut [f32], b: &[f32], mul: f32) {
+    madd_with(level(), a, b, mul);
+}
+
+fn dot_with(level: Level, a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len());
+
+    // SAFETY: `level` is only ever a level supported by the running CPU,
+    // and both slices have the same length.
+    match level {
+        Level::Scalar => scalar::dot(a, b),
+        #[cfg(target_arch = "x86_64")]
+        Level::Sse2 => unsafe { x86::dot_sse2(a, b) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { x86::dot_avx2(a, b) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx512 => unsafe { x86::dot_avx512(a, b) },
+    }
+}
+
+fn madd_with(level: Level, a: &mut [f32], b: &[f32], mul: f32) {
+    assert_eq!(a.len(), b.len());
+
+    // SAFETY: as for `dot_with`.
+    match level {
+        Level::Scalar => scalar::madd(a, b, mul),
+        #[cfg(target_arch = "x86_64")]
+        Level::Sse2 => unsafe { x86::madd_sse2(a, b, mul) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { x86::madd_avx2(a, b, mul) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx512 => unsafe { x86::madd_avx512(a, b, mul) },
+    }
+}
+
+/// Adds the products of elements from `start` onwards to their partial sums.
+fn dot_tail(partial: &mut [f32; LANES], a: &[f32], b: &[f32], start: usize) {
+    for i in start..a.len() {
+        partial[i % LANES] += a[i] * b[i];
+    }
+}
+
+fn reduce(mut partial: [f32; LANES]) -> f32 {
+    let mut width = LANES / 2;
+    while width > 0 {
+        for i in 0..width {
+            partial[i] += partial[i + width];
+        }
+        width /= 2;
+    }
+
+    partial[0]
+}
+
+mod scalar {
+    use super::{dot_tail, reduce, LANES};
+
+    pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+        let mut partial = [0.0; LANES];
+        dot_tail(&mut partial, a, b, 0);
+        reduce(partial)
+    }
+
+    pub fn madd(a: &mut [f32], b: &[f32], mul: f32) {
+        for (i, j) in a.iter_mut().zip(b.iter()) {
+            *i += mul * *j;
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::{dot_tail, reduce, LANES};
+
+    /// Generates a dot product and multiply-add for registers of `$width` lanes.
+    macro_rules! kernels {
+        (
+            $feature:literal, $dot:ident, $madd:ident, $width:literal,
+            $zero:ident, $load:ident, $store:ident, $set1:ident, $add:ident, $mul:ident
+        ) => {
+            #[target_feature(enable = $feature)]
+            pub unsafe fn $dot(a: &[f32], b: &[f32]) -> f32 {
+                const REGS: usize = LANES / $width;
+                let chunks = a.len() / LANES;
+                let (a_ptr, b_ptr) = (a.as_ptr(), b.as_ptr());
+                let mut acc = [$zero(); REGS];
+
+                for chunk in 0..chunks {
+                    for (r, acc) in acc.iter_mut().enumerate() {
+                        let i = chunk * LANES + r * $width;
+                        let prod = $mul($load(a_ptr.add(i)), $load(b_ptr.add(i)));
+                        *acc = $add(*acc, prod);
+                    }
+                }
+
+                let mut partial = [0.0; LANES];
+                for (r, acc) in acc.iter().enumerate() {
+                    $store(partial.as_mut_ptr().add(r * $width), *acc);
+                }
+
+                dot_tail(&mut partial, a, b, chunks * LANES);
+                reduce(partial)
+            }
+
+            #[target_feature(enable = $feature)]
+            pub unsafe fn $madd(a: &mut [f32], b: &[f32], mul: f32) {
+                let chunks = a.len() / $width;
+                let (a_ptr, b_ptr) = (a.as_mut_ptr(), b.as_ptr());
+                let mul_reg = $set1(mul);
+
+                for chunk in 0..chunks {
+                    let i = chunk * $width;
+                    let prod = $mul(mul_reg, $load(b_ptr.add(i)));
+                    $store(a_ptr.add(i), $add($load(a_ptr.add(i)), prod));
+                }
+
+                for i in chunks * $width..a.len() {
+                    a[i] += mul * b[i];
+                }
+            }
+        };
+    }
+
+    kernels!(
+        "sse2",
+        dot_sse2,
+        madd_sse2,
+        4,
+        _mm_setzero_ps,
+        _mm_loadu_ps,
+        _mm_storeu_ps,
+        _mm_set1_ps,
+        _mm_add_ps,
+        _mm_mul_ps
+    );
+
+    kernels!(
+        "avx2",
+        dot_avx2,
+        madd_avx2,
+        8,
+        _mm256_setzero_ps,
+        _mm256_loadu_ps,
+        _mm256_storeu_ps,
+        _mm256_set1_ps,
+        _mm256_add_ps,
+        _mm256_mul_ps
+    );
+
+    kernels!(
+        "avx512f",
+        dot_avx512,
+        madd_avx512,
+        16,
+        _mm512_setzero_ps,
+        _mm512_loadu_ps,
+        _mm512_storeu_ps,
+        _mm512_set1_ps,
+        _mm512_add_ps,
+        _mm512_mul_ps
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn values(len: usize, seed: u32) -> Vec<f32> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as f32 / u32::MAX as f32 * 20.0 - 10.0
+            })
+            .collect()
+    }
+
+    fn lengths() -> impl Iterator<Item = usize> {
+        (0..=70).chain([255, 256, 257, 768, 1023])
+    }
+
+    #[test]
+    fn dot_matches_scalar() {
+        for len in lengths() {
+            let (a, b) = (values(len, 0x1234_5678), values(len, 0x9abc_def0));
+            let expected = scalar::dot(&a, &b);
+
+            for level in Level::supported() {
+                let actual = dot_with(level, &a, &b);
+                assert_eq!(actual.to_bits(), expected.to_bits(), "{level:?}, len {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn madd_matches_scalar() {
+        for len in lengths() {
+            let b = values(len, 0x0bad_cafe);
+            let mut expected = values(len, 0x1357_9bdf);
+            scalar::madd(&mut expected, &b, 0.3);
+
+            for level in Level::supported() {
+                let mut actual = values(len, 0x1357_9bdf);
+                madd_with(level, &mut actual, &b, 0.3);
+
+                let actual = actual.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+                let expected = expected.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+                assert_eq!(actual, expected, "{level:?}, len {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn detects_supported_level() {
+        assert!(Level::supported().contains(&Level::detect()));
+    }
+}
@@ -0,0 +1,235 @@
+//! Multi-threaded training loop.
+
+use std::{
+    convert::Infallible,
+    io::{Read, Result, Write},
+    ops::AddAssign,
+};
+
+use crate::{
+    format::{read_network, read_tag, write_network, write_tag},
+    optimizer::{Optimizer, OptimizerState, TouchedRows},
+    quantise::{Quantise, QuantiseError, Quantiser, Scales},
+    FeedForwardNetwork, Loss, LrScheduler, OutputLayer,
+};
+
+/// Trains a network on batches of `(input, target)` pairs, splitting each
+/// batch across `threads` workers and summing their gradients.
+pub struct Trainer<N, O, L, S> {
+    net: Box<N>,
+    state: OptimizerState<N>,
+    optimizer: O,
+    loss: L,
+    scheduler: S,
+    threads: usize,
+}
+
+impl<N, O, L, S> Trainer<N, O, L, S>
+where
+    N: FeedForwardNetwork + Send + Sync + for<'a> AddAssign<&'a N>,
+    N::InputType: Sync,
+    O: Optimizer,
+    L: Loss<N::OutputType> + Sync,
+    L::Target: Sync,
+    S: LrScheduler,
+{
+    pub fn new(net: Box<N>, optimizer: O, loss: L, scheduler: S, threads: usize) -> Self {
+        Self {
+            net,
+            state: OptimizerState::new(),
+            optimizer,
+            loss,
+            scheduler,
+            threads: threads.max(1),
+        }
+    }
+
+    /// Writes the network, optimiser state and schedule, so that
+    /// training can be resumed with `load_checkpoint`.
+    pub fn save_checkpoint<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_tag(writer, b"CKPT")?;
+        write_network(writer, self.net.as_ref())?;
+        self.state.save(writer)?;
+        self.scheduler.save(writer)
+    }
+
+    pub fn load_checkpoint<R: Read>(
+        reader: &mut R,
+        optimizer: O,
+        loss: L,
+        threads: usize,
+    ) -> Result<Self> {
+        read_tag(reader, b"CKPT")?;
+
+        Ok(Self {
+            net: read_network(reader)?,
+            state: OptimizerState::load(reader)?,
+            scheduler: S::load(reader)?,
+            optimizer,
+            loss,
+            threads: threads.max(1),
+        })
+    }
+
+    pub fn network(&self) -> &N {
+        &self.net
+    }
+
+    pub fn into_network(self) -> Box<N> {
+        self.net
+    }
+
+    pub fn state(&self) -> &OptimizerState<N> {
+        &self.state
+    }
+
+    /// Number of batches trained on so far.
+    pub fn batches(&self) -> usize {
+        self.state.steps() as usize
+    }
+
+    /// Runs a single optimiser step on `batch`, returning the mean loss.
+    pub fn train_batch(&mut self, batch: &[(N::InputType, L::Target)]) -> f32 {
+        let forward = |net: &N, inputs: &[&N::InputType]| {
+            Ok::<_, Infallible>(net.out_with_layers_batch(inputs))
+        };
+
+        match self.train_batch_with(batch, forward) {
+            Ok(loss) => loss,
+            Err(never) => match never {},
+        }
+    }
+
+    fn train_batch_with<E, F>(
+        &mut self,
+        batch: &[(N::InputType, L::Target)],
+        forward: F,
+    ) -> std::result::Result<f32, E>
+    where
+        E: Send,
+        F: Fn(&N, &[&N::InputType]) -> std::result::Result<Vec<N::Layers>, E> + Sync,
+    {
+        if batch.is_empty() {
+            return Ok(0.0);
+        }
+
+        let chunk_size = batch.len().div_ceil(self.threads);
+        let net = &*self.net;
+        let loss_fn = &self.loss;
+        let forward = &forward;
+
+        let (grad, touched, loss) = std::thread::scope(|s| {
+            let handles = batch
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    s.spawn(move || {
+                        let mut grad = N::boxed_and_zeroed();
+                        let mut loss = 0.0;
+
+                        let inputs = chunk.iter().map(|(input, _)| input).collect::<Vec<_>>();
+                        let layers = forward(net, &inputs)?;
+
+                        let mut errs = Vec::with_capacity(chunk.len());
+                        for ((_, target), layers) in chunk.iter().zip(&layers) {
+                            let (this_loss, err) = loss_fn.loss(&layers.output_layer(), target);
+                            errs.push(err);
+                            loss += this_loss;
+                        }
+
+                        net.backprop_batch(&inputs, &mut grad, errs, &layers);
+
+                        let touched = N::LAZY.then(|| {
+                            let mut touched = TouchedRows::default();
+                            for input in inputs {
+                                N::touched_rows(input, &mut touched);
+                            }
+                            touched
+                        });
+
+                        Ok((grad, touched, loss))
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let mut total = None::<(Box<N>, Option<TouchedRows>, f32)>;
+            for handle in handles {
+                let (grad, touched, loss) = handle.join().unwrap()?;
+                match total.as_mut() {
+                    Some((total_grad, total_touched, total_loss)) => {
+                        **total_grad += &grad;
+                        if let (Some(total_touched), Some(touched)) = (total_touched, &touched) {
+                            *total_touched += touched;
+                        }
+                        *total_loss += loss;
+                    }
+                    None => total = Some((grad, touched, loss)),
+                }
+            }
+
+            Ok(total.unwrap())
+        })?;
+
+        let lr = self.scheduler.lr(self.batches());
+        let adj = 1.0 / batch.len() as f32;
+        self.state.step(
+            &mut self.net,
+            &grad,
+            &self.optimizer,
+            adj,
+            lr,
+            touched.as_ref(),
+        );
+
+        Ok(loss / batch.len() as f32)
+    }
+
+    /// Trains on each batch of a superbatch in turn, returning the mean loss across them.
+    pub fn train_superbatch<'a, I>(&mut self, batches: I) -> f32
+    where
+        I: IntoIterator<Item = &'a [(N::InputType, L::Target)]>,
+        N::InputType: 'a,
+        L::Target: 'a,
+    {
+        let mut loss = 0.0;
+        let mut count = 0;
+
+        for batch in batches {
+            loss += self.train_batch(batch);
+            count += 1;
+        }
+
+        loss / count.max(1) as f32
+    }
+}
+
+impl<N, O, L, S> Trainer<N, O, L, S>
+where
+    N: Quantise + Send + Sync + for<'a> AddAssign<&'a N>,
+    N::InputType: Sync,
+    O: Optimizer,
+    L: Loss<N::OutputType> + Sync,
+    L::Target: Sync,
+    S: LrScheduler,
+{
+    /// Runs a single optimiser step on `batch` with a fake-quantised forward pass,
+    /// see [`Quantise::out_with_layers_fake_quantised`], returning the mean loss.
+    ///
+    /// `scales` are given to the network itself, as in [`Quantise::quantise`].
+    pub fn train_batch_fake_quantised(
+        &mut self,
+        batch: &[(N::InputType, L::Target)],
+        q: &Quantiser,
+        input_scale: i32,
+        scales: Scales,
+    ) -> std::result::Result<f32, QuantiseError> {
+        self.train_batch_with(batch, |net, inputs| {
+            inputs
+                .iter()
+                .map(|&input| {
+                    net.out_with_layers_fake_quantised(input, q, input_scale, scales)
+                        .map(|(layers, _)| layers)
+                })
+                .collect()
+        })
+    }
+}
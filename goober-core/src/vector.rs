@@ -1,4 +1,8 @@
-use crate::activation::Activation;
+use crate::{
+    activation::Activation,
+    optimizer::{Optimizer, Step},
+    simd,
+};
 
 /// Sparse representation of a vector, storing active
 /// indices instead of a value for each index in the vector.
@@ -138,12 +142,7 @@ impl<const N: usize> Vector<N> {
     }
 
     pub fn dot(&self, other: &Vector<N>) -> f32 {
-        let mut score = 0.0;
-        for (&i, &j) in self.inner.iter().zip(other.inner.iter()) {
-            score += i * j;
-        }
-
-        score
+        simd::dot(&self.inner, &other.inner)
     }
 
     pub fn out<T: Activation>(&self, other: &Vector<N>) -> f32 {
@@ -171,6 +170,12 @@ impl<const N: usize> Vector<N> {
         Self::from_raw([0.0; N])
     }
 
+    pub fn clamp(&mut self, min: f32, max: f32) {
+        for i in self.inner.iter_mut() {
+            *i = i.clamp(min, max);
+        }
+    }
+
     pub fn activate<T: Activation>(mut self) -> Self {
         for i in self.inner.iter_mut() {
             *i = T::activate(*i);
@@ -187,19 +192,18 @@ impl<const N: usize> Vector<N> {
         self
     }
 
-    pub fn adam(&mut self, mut g: Self, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
-        const B1: f32 = 0.9;
-        const B2: f32 = 0.999;
-
-        g = adj * g;
-        *m = B1 * *m + (1. - B1) * g;
-        *v = B2 * *v + (1. - B2) * g * g;
-        *self -= lr * *m / (v.sqrt() + 0.000_000_01);
+    pub fn update<O: Optimizer>(
+        &mut self,
+        g: &Self,
+        m: &mut Self,
+        v: &mut Self,
+        opt: &O,
+        step: &Step,
+    ) {
+        opt.update(&mut self.inner, &g.inner, &mut m.inner, &mut v.inner, step);
     }
 
     pub fn madd(&mut self, other: &Self, mul: f32) {
-        for (i, j) in self.inner.iter_mut().zip(other.inner.iter()) {
-            *i += mul * *j;
-        }
+        simd::madd(&mut self.inner, &other.inner, mul);
     }
 }
@@ -1,8 +1,8 @@
 use proc_macro2::{Ident, Span, TokenStream};
-use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Fields};
+use quote::{quote, quote_spanned};
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Expr, Field, Fields};
 
-#[proc_macro_derive(FeedForwardNetwork)]
+#[proc_macro_derive(FeedForwardNetwork, attributes(optimizer))]
 pub fn network_utils(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = input.ident;
@@ -15,10 +15,16 @@ pub fn network_utils(input: proc_macro::TokenStream) -> proc_macro::TokenStream
     let output_type = gen_output_type(&input.data);
     let output_layer = gen_output_layer(&input.data);
 
-    let adam_expr = gen_adam_expr(&input.data);
+    let update_expr = gen_update_expr(&input.data);
+    let layer_shapes_expr = gen_layer_shapes_expr(&input.data);
+    let touched_rows_expr = gen_touched_rows_expr(&input.data);
+    let lazy_expr = gen_lazy_expr(&input.data);
     let layer_exprs = gen_layer_exprs(&input.data);
     let layer_exprs_fields = gen_layer_exprs_fields(&input.data);
     let backprop_exprs = gen_backprop_exprs(&input.data);
+    let out_from_first_layer = gen_out_from_first_layer(&name, &input.data);
+    let batch_layer_exprs = gen_batch_layer_exprs(&input.data);
+    let batch_backprop_exprs = gen_batch_backprop_exprs(&input.data);
 
     let expanded = quote! {
         impl std::ops::AddAssign<& #name> for #name {
@@ -35,13 +41,32 @@ pub fn network_utils(input: proc_macro::TokenStream) -> proc_macro::TokenStream
             #output_layer
         }
 
+        #out_from_first_layer
+
         impl goober::FeedForwardNetwork for #name {
             type InputType = #input_type;
             type OutputType = #output_type;
             type Layers = #layer_name;
 
-            fn adam(&mut self, g: &Self, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
-                #adam_expr
+            const LAZY: bool = #lazy_expr;
+
+            fn update<O: goober::Optimizer>(
+                &mut self,
+                g: &Self,
+                m: &mut Self,
+                v: &mut Self,
+                opt: &O,
+                step: &goober::Step,
+            ) {
+                #update_expr
+            }
+
+            fn layer_shapes(shapes: &mut Vec<goober::LayerShape>) {
+                #layer_shapes_expr
+            }
+
+            fn touched_rows(input: &Self::InputType, rows: &mut goober::TouchedRows) {
+                #touched_rows_expr
             }
 
             fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
@@ -62,6 +87,101 @@ pub fn network_utils(input: proc_macro::TokenStream) -> proc_macro::TokenStream
                 use goober::OutputLayer as __InternalOutputLayer;
                 #backprop_exprs
             }
+
+            fn out_with_layers_batch<I: std::borrow::Borrow<Self::InputType>>(
+                &self,
+                inputs: &[I],
+            ) -> Vec<Self::Layers> {
+                use goober::OutputLayer as __InternalOutputLayer;
+                #batch_layer_exprs
+            }
+
+            fn backprop_batch<
+                I: std::borrow::Borrow<Self::InputType>,
+                B: std::borrow::Borrow<Self::Layers>,
+            >(
+                &self,
+                inputs: &[I],
+                grad: &mut Self,
+                errs: Vec<Self::OutputType>,
+                layers: &[B],
+            ) -> Vec<Self::InputType> {
+                use goober::OutputLayer as __InternalOutputLayer;
+                let layers = layers
+                    .iter()
+                    .map(std::borrow::Borrow::borrow)
+                    .collect::<Vec<&Self::Layers>>();
+                #batch_backprop_exprs
+            }
+        }
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
+
+#[proc_macro_derive(Quantise, attributes(quantise))]
+pub fn quantise(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = input.ident;
+    let quantised_name = Ident::new((name.to_string() + "Quantised").as_str(), Span::call_site());
+
+    let quantised_fields = gen_quantised_fields(&input.data);
+    let quantised_input_type = gen_quantised_input_type(&input.data);
+    let quantised_output_type = gen_quantised_output_type(&input.data);
+    let quantised_out_exprs = gen_quantised_out_exprs(&input.data);
+    let output_scale_expr = gen_output_scale_expr(&input.data);
+    let quantise_exprs = gen_quantise_exprs(&input.data);
+    let quantise_input_expr = gen_quantise_input_expr(&input.data);
+    let fake_quantised_exprs = gen_fake_quantised_exprs(&input.data);
+
+    let expanded = quote! {
+        pub struct #quantised_name {
+            #quantised_fields
+        }
+
+        impl goober::QuantisedNetwork for #quantised_name {
+            type InputType = #quantised_input_type;
+            type OutputType = #quantised_output_type;
+
+            fn out(&self, input: &Self::InputType) -> Self::OutputType {
+                #quantised_out_exprs
+            }
+
+            fn output_scale(&self) -> i32 {
+                #output_scale_expr
+            }
+        }
+
+        impl goober::Quantise for #name {
+            type Quantised = #quantised_name;
+
+            fn quantise(
+                &self,
+                q: &goober::quantise::Quantiser,
+                input_scale: i32,
+                scales: goober::quantise::Scales,
+            ) -> Result<Self::Quantised, goober::quantise::QuantiseError> {
+                #quantise_exprs
+            }
+
+            fn out_with_layers_fake_quantised(
+                &self,
+                input: &Self::InputType,
+                q: &goober::quantise::Quantiser,
+                input_scale: i32,
+                scales: goober::quantise::Scales,
+            ) -> Result<(Self::Layers, i32), goober::quantise::QuantiseError> {
+                use goober::OutputLayer as __InternalOutputLayer;
+                #fake_quantised_exprs
+            }
+
+            fn quantise_input(
+                q: &goober::quantise::Quantiser,
+                input: &Self::InputType,
+                input_scale: i32,
+            ) -> #quantised_input_type {
+                #quantise_input_expr
+            }
         }
     };
 
@@ -132,11 +252,127 @@ fn gen_input_type(data: &Data) -> TokenStream {
     })
 }
 
-fn gen_adam_expr(data: &Data) -> TokenStream {
+fn gen_touched_rows_expr(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let ty = &fields.named.first().unwrap().ty;
+        quote!(<#ty as goober::FeedForwardNetwork>::touched_rows(input, rows))
+    })
+}
+
+/// Per-field options given by `#[optimizer(lr_scale = .., weight_decay = .., clip = .., lazy)]`.
+///
+/// `clip` takes either a single bound `c`, clamping weights to `[-c, c]`, or a pair `(min, max)`.
+/// `lazy` only updates the weights of sparse input features active in the batch,
+/// and is rejected on fields that are not `LazyUpdate` layers.
+#[derive(Default)]
+struct OptimizerOptions {
+    lr_scale: Option<Expr>,
+    weight_decay: Option<Expr>,
+    clip: Option<(Expr, Expr)>,
+    lazy: bool,
+}
+
+fn optimizer_options(f: &Field) -> syn::Result<OptimizerOptions> {
+    let mut opts = OptimizerOptions::default();
+
+    for attr in f.attrs.iter().filter(|a| a.path().is_ident("optimizer")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("lr_scale") {
+                opts.lr_scale = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("weight_decay") {
+                opts.weight_decay = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("lazy") {
+                opts.lazy = true;
+            } else if meta.path.is_ident("clip") {
+                opts.clip = Some(match meta.value()?.parse()? {
+                    Expr::Tuple(bounds) if bounds.elems.len() == 2 => {
+                        (bounds.elems[0].clone(), bounds.elems[1].clone())
+                    }
+                    Expr::Tuple(bounds) => {
+                        return Err(syn::Error::new_spanned(bounds, "expected `(min, max)`"))
+                    }
+                    bound => (syn::parse_quote!(-(#bound)), bound),
+                });
+            } else {
+                return Err(meta.error("unsupported optimizer option"));
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(opts)
+}
+
+fn gen_update_expr(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let recurse = fields.named.iter().map(|f| {
+            let name = &f.ident;
+            let ty = &f.ty;
+            let opts = match optimizer_options(f) {
+                Ok(opts) => opts,
+                Err(err) => return err.to_compile_error(),
+            };
+
+            let mut check = None;
+            let mut overrides = Vec::new();
+            if let Some(lr_scale) = opts.lr_scale {
+                overrides.push(quote!(.with_lr_scale(#lr_scale)));
+            }
+            if let Some(weight_decay) = opts.weight_decay {
+                overrides.push(quote!(.with_weight_decay(#weight_decay)));
+            }
+            if let Some((min, max)) = opts.clip {
+                overrides.push(quote!(.with_weight_clip(#min, #max)));
+            }
+            if opts.lazy {
+                check = Some(quote_spanned! {ty.span()=> {
+                    fn lazy_update<T: goober::LazyUpdate>() {}
+                    lazy_update::<#ty>();
+                }});
+                overrides.push(quote!(.with_lazy()));
+            }
+
+            let group = if overrides.is_empty() {
+                quote!(step)
+            } else {
+                quote!(&step #(#overrides)*)
+            };
+
+            quote! {
+                #check
+                self.#name.update(&g.#name, &mut m.#name, &mut v.#name, opt, #group);
+            }
+        });
+        quote!(#(#recurse)*)
+    })
+}
+
+fn gen_lazy_expr(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let recurse = fields.named.iter().map(|f| {
+            let ty = &f.ty;
+            if optimizer_options(f).is_ok_and(|opts| opts.lazy) {
+                quote!(true)
+            } else {
+                quote!(<#ty as goober::FeedForwardNetwork>::LAZY)
+            }
+        });
+        quote!(false #(|| #recurse)*)
+    })
+}
+
+fn gen_layer_shapes_expr(data: &Data) -> TokenStream {
     struct_with_fields_only!(|data, fields| {
         let recurse = fields.named.iter().map(|f| {
             let name = &f.ident;
-            quote!(self.#name.adam(&g.#name, &mut m.#name, &mut v.#name, adj, lr);)
+            let ty = &f.ty;
+            quote! {
+                let start = shapes.len();
+                <#ty as goober::FeedForwardNetwork>::layer_shapes(shapes);
+                for shape in &mut shapes[start..] {
+                    shape.nest(stringify!(#name), std::mem::offset_of!(Self, #name));
+                }
+            }
         });
         quote!(#(#recurse)*)
     })
@@ -169,6 +405,263 @@ fn gen_layer_exprs_fields(data: &Data) -> TokenStream {
     })
 }
 
+/// Generates `out_from_first_layer`, which runs every layer after the first
+/// given the first layer's output, e.g. from an incrementally updated accumulator.
+fn gen_out_from_first_layer(name: &Ident, data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        if fields.named.len() < 2 {
+            return quote!();
+        }
+
+        let first = &fields.named.first().unwrap().ty;
+        let second = &fields.named[1].ident;
+        let recurse = fields.named.iter().skip(2).map(|f| {
+            let field = &f.ident;
+            quote!(let out = goober::FeedForwardNetwork::out(&self.#field, &out);)
+        });
+
+        quote! {
+            impl #name {
+                /// Output of the network given the output of its first layer.
+                pub fn out_from_first_layer(
+                    &self,
+                    first: &<#first as goober::FeedForwardNetwork>::OutputType,
+                ) -> <Self as goober::FeedForwardNetwork>::OutputType {
+                    let out = goober::FeedForwardNetwork::out(&self.#second, first);
+                    #(#recurse)*
+                    out
+                }
+            }
+        }
+    })
+}
+
+fn gen_quantised_fields(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let recurse = fields.named.iter().map(|f| {
+            let name = &f.ident;
+            let ty = &f.ty;
+            quote!(#name: <#ty as goober::Quantise>::Quantised,)
+        });
+        quote!(#(#recurse)*)
+    })
+}
+
+fn gen_quantised_input_type(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let ty = &fields.named.first().unwrap().ty;
+        quote!(<<#ty as goober::Quantise>::Quantised as goober::QuantisedNetwork>::InputType)
+    })
+}
+
+fn gen_quantised_output_type(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let ty = &fields.named.last().unwrap().ty;
+        quote!(<<#ty as goober::Quantise>::Quantised as goober::QuantisedNetwork>::OutputType)
+    })
+}
+
+fn gen_quantised_out_exprs(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let first = &fields.named.first().unwrap().ident;
+        let recurse = fields.named.iter().skip(1).map(|f| {
+            let name = &f.ident;
+            quote!(let out = goober::QuantisedNetwork::out(&self.#name, &out);)
+        });
+
+        quote! {
+            let out = goober::QuantisedNetwork::out(&self.#first, input);
+            #(#recurse)*
+            out
+        }
+    })
+}
+
+fn gen_output_scale_expr(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let last = &fields.named.last().unwrap().ident;
+        quote!(goober::QuantisedNetwork::output_scale(&self.#last))
+    })
+}
+
+/// Per-field scales given by `#[quantise(weights = .., output = ..)]`.
+///
+/// Scales not given on the field fall back to the `scales` passed to the whole
+/// network, with `output` only applying to the last field.
+fn quantise_scales(f: &Field, last: bool) -> syn::Result<TokenStream> {
+    let mut weights = quote!(scales.weights);
+    let mut output = if last {
+        quote!(scales.output)
+    } else {
+        quote!(None)
+    };
+
+    for attr in f.attrs.iter().filter(|a| a.path().is_ident("quantise")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("weights") {
+                let scale: Expr = meta.value()?.parse()?;
+                weights = quote!(Some(#scale));
+            } else if meta.path.is_ident("output") {
+                let scale: Expr = meta.value()?.parse()?;
+                output = quote!(Some(#scale));
+            } else {
+                return Err(meta.error("unsupported quantise option"));
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(quote!(goober::quantise::Scales { weights: #weights, output: #output }))
+}
+
+fn gen_quantise_exprs(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let mut prev = None;
+        let count = fields.named.len();
+        let recurse = fields.named.iter().enumerate().map(|(i, f)| {
+            let name = &f.ident;
+            let scales = match quantise_scales(f, i + 1 == count) {
+                Ok(scales) => scales,
+                Err(err) => return err.to_compile_error(),
+            };
+
+            let input_scale = match prev {
+                Some(prev) => quote!(goober::QuantisedNetwork::output_scale(&#prev)),
+                None => quote!(input_scale),
+            };
+            prev = Some(name);
+
+            quote! {
+                let #name = goober::Quantise::quantise(&self.#name, q, #input_scale, #scales)
+                    .map_err(|err| err.nest(stringify!(#name)))?;
+            }
+        });
+
+        let names = fields.named.iter().map(|f| &f.ident);
+
+        quote! {
+            #(#recurse)*
+            Ok(Self::Quantised { #(#names),* })
+        }
+    })
+}
+
+fn gen_fake_quantised_exprs(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let mut prev = None;
+        let count = fields.named.len();
+        let recurse = fields.named.iter().enumerate().map(|(i, f)| {
+            let name = &f.ident;
+            let scales = match quantise_scales(f, i + 1 == count) {
+                Ok(scales) => scales,
+                Err(err) => return err.to_compile_error(),
+            };
+
+            let input = match prev {
+                Some(prev) => quote!(&#prev.output_layer()),
+                None => quote!(input),
+            };
+            prev = Some(name);
+
+            quote! {
+                let (#name, scale) = goober::Quantise::out_with_layers_fake_quantised(
+                    &self.#name, #input, q, scale, #scales,
+                )
+                .map_err(|err| err.nest(stringify!(#name)))?;
+            }
+        });
+
+        let names = fields.named.iter().map(|f| &f.ident);
+
+        quote! {
+            let scale = input_scale;
+            #(#recurse)*
+            Ok((Self::Layers { #(#names),* }, scale))
+        }
+    })
+}
+
+fn gen_quantise_input_expr(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let ty = &fields.named.first().unwrap().ty;
+        quote!(<#ty as goober::Quantise>::quantise_input(q, input, input_scale))
+    })
+}
+
+fn gen_batch_layer_exprs(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let mut prev = &None;
+        let recurse = fields.named.iter().enumerate().map(|(i, f)| {
+            let name = &f.ident;
+            let res = if i > 0 {
+                quote! {
+                    let #name = self.#name.out_with_layers_batch(
+                        &#prev.iter().map(|l| l.output_layer()).collect::<Vec<_>>(),
+                    );
+                }
+            } else {
+                quote!(let #name = self.#name.out_with_layers_batch(inputs);)
+            };
+            prev = name;
+            res
+        });
+
+        let iters = fields.named.iter().map(|f| {
+            let name = &f.ident;
+            quote!(let mut #name = #name.into_iter();)
+        });
+
+        let next = fields.named.iter().map(|f| {
+            let name = &f.ident;
+            quote!(#name: #name.next().unwrap(),)
+        });
+
+        quote! {
+            #(#recurse)*
+            #(#iters)*
+            (0..inputs.len()).map(|_| Self::Layers { #(#next)* }).collect()
+        }
+    })
+}
+
+fn gen_batch_backprop_exprs(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let mut prev = &None;
+        let mut list = fields
+            .named
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                let name = &f.ident;
+                let res = if i > 0 {
+                    quote! {
+                        let errs = self.#name.backprop_batch(
+                            &layers.iter().map(|l| l.#prev.output_layer()).collect::<Vec<_>>(),
+                            &mut grad.#name,
+                            errs,
+                            &layers.iter().map(|l| &l.#name).collect::<Vec<_>>(),
+                        );
+                    }
+                } else {
+                    quote! {
+                        self.#name.backprop_batch(
+                            inputs,
+                            &mut grad.#name,
+                            errs,
+                            &layers.iter().map(|l| &l.#name).collect::<Vec<_>>(),
+                        )
+                    }
+                };
+
+                prev = name;
+                res
+            })
+            .collect::<Vec<TokenStream>>();
+        list.reverse();
+        quote!(#(#list)*)
+    })
+}
+
 fn gen_backprop_exprs(data: &Data) -> TokenStream {
     struct_with_fields_only!(|data, fields| {
         let mut prev = &None;
@@ -0,0 +1,82 @@
+use goober_core::{activation::Activation, SparseVector, Vector};
+
+use crate::{Perspective, SparseConnected};
+
+/// Running pre-activation sums of a [`SparseConnected`] layer, updated
+/// incrementally as features are added and removed instead of recomputed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Accumulator<const N: usize> {
+    values: Vector<N>,
+}
+
+impl<const N: usize> Accumulator<N> {
+    /// Accumulator with no active features.
+    pub fn new<T: Activation, const M: usize>(layer: &SparseConnected<T, M, N>) -> Self {
+        Self {
+            values: layer.bias(),
+        }
+    }
+
+    pub fn from_features<T: Activation, const M: usize>(
+        layer: &SparseConnected<T, M, N>,
+        input: &SparseVector,
+    ) -> Self {
+        let mut acc = Self::new(layer);
+        acc.refresh(layer, input);
+        acc
+    }
+
+    pub fn add_feature<T: Activation, const M: usize>(
+        &mut self,
+        layer: &SparseConnected<T, M, N>,
+        feat: usize,
+    ) {
+        self.values += layer.weights_row(feat);
+    }
+
+    pub fn remove_feature<T: Activation, const M: usize>(
+        &mut self,
+        layer: &SparseConnected<T, M, N>,
+        feat: usize,
+    ) {
+        self.values -= layer.weights_row(feat);
+    }
+
+    /// Recomputes the sums from scratch, e.g. when incremental updates
+    /// would touch more features than are active.
+    pub fn refresh<T: Activation, const M: usize>(
+        &mut self,
+        layer: &SparseConnected<T, M, N>,
+        input: &SparseVector,
+    ) {
+        self.values = layer.bias();
+        for &feat in input.iter() {
+            self.add_feature(layer, feat);
+        }
+    }
+
+    /// Sums before activation.
+    pub fn values(&self) -> Vector<N> {
+        self.values
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize> SparseConnected<T, M, N> {
+    /// Output of the layer given accumulated sums, equal to `out` for the
+    /// features the accumulator was built from.
+    pub fn out_from_accumulator(&self, acc: &Accumulator<N>) -> Vector<N> {
+        acc.values.activate::<T>()
+    }
+}
+
+impl<T: Activation, const M: usize, const NL: usize, const N: usize>
+    Perspective<SparseConnected<T, M, NL>, N>
+{
+    /// Output of the layer given accumulated sums for both inputs.
+    pub fn out_from_accumulators(&self, us: &Accumulator<NL>, them: &Accumulator<NL>) -> Vector<N> {
+        crate::concat::concat(
+            &self.inner().out_from_accumulator(us),
+            &self.inner().out_from_accumulator(them),
+        )
+    }
+}
@@ -0,0 +1,71 @@
+use std::marker::PhantomData;
+
+use goober_core::{
+    activation::{self, VectorActivation},
+    FeedForwardNetwork, LayerShape, Optimizer, OutputLayer, Step, Vector,
+};
+
+/// Applies a vector activation to its input, with no parameters.
+#[derive(Clone, Copy, Default)]
+pub struct Activate<A, const N: usize> {
+    phantom: PhantomData<A>,
+}
+
+/// Softmax output layer, to be trained with [`goober_core::loss::CrossEntropy`].
+pub type Softmax<const N: usize> = Activate<activation::Softmax, N>;
+
+/// Log-softmax output layer, to be trained with [`goober_core::loss::NegativeLogLikelihood`].
+pub type LogSoftmax<const N: usize> = Activate<activation::LogSoftmax, N>;
+
+impl<A, const N: usize> Activate<A, N> {
+    pub const fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<A, const N: usize> std::ops::AddAssign<&Activate<A, N>> for Activate<A, N> {
+    fn add_assign(&mut self, _: &Activate<A, N>) {}
+}
+
+pub struct ActivateLayers<const N: usize> {
+    out: Vector<N>,
+}
+
+impl<const N: usize> OutputLayer<Vector<N>> for ActivateLayers<N> {
+    fn output_layer(&self) -> Vector<N> {
+        self.out
+    }
+}
+
+impl<A: VectorActivation, const N: usize> FeedForwardNetwork for Activate<A, N> {
+    type InputType = Vector<N>;
+    type OutputType = Vector<N>;
+    type Layers = ActivateLayers<N>;
+
+    fn update<O: Optimizer>(&mut self, _: &Self, _: &mut Self, _: &mut Self, _: &O, _: &Step) {}
+
+    fn layer_shapes(shapes: &mut Vec<LayerShape>) {
+        shapes.push(LayerShape::new::<Self>(
+            &format!("Activate<{}>", A::NAME),
+            &[N],
+        ));
+    }
+
+    fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        Self::Layers {
+            out: A::activate(input),
+        }
+    }
+
+    fn backprop(
+        &self,
+        _: &Self::InputType,
+        _: &mut Self,
+        out_err: Self::OutputType,
+        layers: &Self::Layers,
+    ) -> Self::InputType {
+        A::backprop(&layers.out, &out_err)
+    }
+}
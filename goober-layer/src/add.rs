@@ -1,4 +1,4 @@
-use goober_core::{FeedForwardNetwork, OutputLayer};
+use goober_core::{FeedForwardNetwork, LayerShape, Optimizer, OutputLayer, Step};
 
 /// Adds two sub-networks that have common inputs and outputs.
 #[repr(C)]
@@ -50,9 +50,23 @@ where
     type OutputType = A::OutputType;
     type Layers = AddLayers<A, B>;
 
-    fn adam(&mut self, g: &Self, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
-        self.a.adam(&g.a, &mut m.a, &mut v.a, adj, lr);
-        self.b.adam(&g.b, &mut m.b, &mut v.b, adj, lr);
+    fn update<O: Optimizer>(&mut self, g: &Self, m: &mut Self, v: &mut Self, opt: &O, step: &Step) {
+        self.a.update(&g.a, &mut m.a, &mut v.a, opt, step);
+        self.b.update(&g.b, &mut m.b, &mut v.b, opt, step);
+    }
+
+    fn layer_shapes(shapes: &mut Vec<LayerShape>) {
+        let start = shapes.len();
+        A::layer_shapes(shapes);
+        for shape in &mut shapes[start..] {
+            shape.nest("a", std::mem::offset_of!(Self, a));
+        }
+
+        let start = shapes.len();
+        B::layer_shapes(shapes);
+        for shape in &mut shapes[start..] {
+            shape.nest("b", std::mem::offset_of!(Self, b));
+        }
     }
 
     fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
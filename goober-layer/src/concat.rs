@@ -0,0 +1,138 @@
+use goober_core::{
+    FeedForwardNetwork, LayerShape, LazyUpdate, Optimizer, OutputLayer, Step, TouchedRows, Vector,
+};
+
+/// Concatenates the outputs of two sub-networks that have common inputs,
+/// `N` must be the sum of their output sizes.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Concat<A, B, const N: usize> {
+    a: A,
+    b: B,
+}
+
+impl<A, B, const N: usize> std::ops::AddAssign<&Concat<A, B, N>> for Concat<A, B, N>
+where
+    for<'a> A: FeedForwardNetwork + std::ops::AddAssign<&'a A>,
+    for<'a> B: FeedForwardNetwork + std::ops::AddAssign<&'a B>,
+{
+    fn add_assign(&mut self, rhs: &Concat<A, B, N>) {
+        self.a += &rhs.a;
+        self.b += &rhs.b;
+    }
+}
+
+pub struct ConcatLayers<A, B, const N: usize>
+where
+    A: FeedForwardNetwork,
+    B: FeedForwardNetwork,
+{
+    a: A::Layers,
+    b: B::Layers,
+}
+
+impl<A, B, const NA: usize, const NB: usize, const N: usize> OutputLayer<Vector<N>>
+    for ConcatLayers<A, B, N>
+where
+    A: FeedForwardNetwork<OutputType = Vector<NA>>,
+    B: FeedForwardNetwork<OutputType = Vector<NB>>,
+{
+    fn output_layer(&self) -> Vector<N> {
+        concat(&self.a.output_layer(), &self.b.output_layer())
+    }
+}
+
+impl<A, B, const NA: usize, const NB: usize, const N: usize> FeedForwardNetwork for Concat<A, B, N>
+where
+    A: FeedForwardNetwork<OutputType = Vector<NA>>,
+    B: FeedForwardNetwork<InputType = A::InputType, OutputType = Vector<NB>>,
+    A::InputType: std::ops::Add<A::InputType, Output = A::InputType>,
+{
+    type InputType = A::InputType;
+    type OutputType = Vector<N>;
+    type Layers = ConcatLayers<A, B, N>;
+
+    const LAZY: bool = A::LAZY || B::LAZY;
+
+    fn update<O: Optimizer>(&mut self, g: &Self, m: &mut Self, v: &mut Self, opt: &O, step: &Step) {
+        self.a.update(&g.a, &mut m.a, &mut v.a, opt, step);
+        self.b.update(&g.b, &mut m.b, &mut v.b, opt, step);
+    }
+
+    fn layer_shapes(shapes: &mut Vec<LayerShape>) {
+        let start = shapes.len();
+        A::layer_shapes(shapes);
+        for shape in &mut shapes[start..] {
+            shape.nest("a", std::mem::offset_of!(Self, a));
+        }
+
+        let start = shapes.len();
+        B::layer_shapes(shapes);
+        for shape in &mut shapes[start..] {
+            shape.nest("b", std::mem::offset_of!(Self, b));
+        }
+    }
+
+    fn touched_rows(input: &Self::InputType, rows: &mut TouchedRows) {
+        A::touched_rows(input, rows);
+        B::touched_rows(input, rows);
+    }
+
+    fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        Self::Layers {
+            a: self.a.out_with_layers(input),
+            b: self.b.out_with_layers(input),
+        }
+    }
+
+    fn backprop(
+        &self,
+        input: &Self::InputType,
+        grad: &mut Self,
+        out_err: Self::OutputType,
+        layers: &Self::Layers,
+    ) -> Self::InputType {
+        let (a_err, b_err) = split(&out_err);
+        let a_back = self.a.backprop(input, &mut grad.a, a_err, &layers.a);
+        let b_back = self.b.backprop(input, &mut grad.b, b_err, &layers.b);
+        a_back + b_back
+    }
+}
+
+impl<A, B, const NA: usize, const NB: usize, const N: usize> LazyUpdate for Concat<A, B, N>
+where
+    A: LazyUpdate<OutputType = Vector<NA>>,
+    B: LazyUpdate<InputType = A::InputType, OutputType = Vector<NB>>,
+    A::InputType: std::ops::Add<A::InputType, Output = A::InputType>,
+{
+}
+
+impl<A, B, const N: usize> Concat<A, B, N> {
+    pub const fn from_raw(a: A, b: B) -> Self {
+        Self { a, b }
+    }
+}
+
+pub(crate) struct Lengths<const NA: usize, const NB: usize, const N: usize>;
+
+impl<const NA: usize, const NB: usize, const N: usize> Lengths<NA, NB, N> {
+    pub(crate) const VALID: () = assert!(
+        NA + NB == N,
+        "concatenated length must be the sum of both outputs"
+    );
+}
+
+pub(crate) fn concat<const NA: usize, const NB: usize, const N: usize>(
+    a: &Vector<NA>,
+    b: &Vector<NB>,
+) -> Vector<N> {
+    let () = Lengths::<NA, NB, N>::VALID;
+    Vector::from_fn(|i| if i < NA { a[i] } else { b[i - NA] })
+}
+
+pub(crate) fn split<const NA: usize, const NB: usize, const N: usize>(
+    v: &Vector<N>,
+) -> (Vector<NA>, Vector<NB>) {
+    let () = Lengths::<NA, NB, N>::VALID;
+    (Vector::from_fn(|i| v[i]), Vector::from_fn(|i| v[NA + i]))
+}
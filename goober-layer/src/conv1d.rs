@@ -1,34 +1,156 @@
 use std::marker::PhantomData;
 
-use goober_core::{FeedForwardNetwork, OutputLayer, Vector, activation::Activation};
+use goober_core::{
+    activation::Activation, FeedForwardNetwork, LayerShape, Matrix, Optimizer, OutputLayer, Step,
+    Vector,
+};
 
-/// Applies a 1D Convolution from input dimension `M` to output dimension `N`.
+/// Padding needed for a convolution with stride 1 to keep the length of its input.
+pub const fn same_padding(kernel: usize, dilation: usize) -> usize {
+    dilation * (kernel - 1) / 2
+}
+
+/// 1D Convolution.
+/// - `T` is the activation function used.
+/// - `M` is the size of the input vector, `C_IN` channels of length `M / C_IN`.
+/// - `N` is the size of the output vector, `C_OUT` channels of length `N / C_OUT`.
+/// - `K` is the size of the kernel.
+/// - `STRIDE`, `DILATION` and `PAD` (zeroes added to each end of the input) are
+///   as usual, see [`same_padding`].
+///
+/// Vectors are stored channel by channel, and the output length must equal
+/// `(M / C_IN + 2 * PAD - DILATION * (K - 1) - 1) / STRIDE + 1`.
 #[repr(C)]
 #[derive(Clone, Copy)]
-pub struct Conv1D<T, const M: usize, const N: usize> {
-    weights: Vector<M>,
-    bias: Vector<N>,
+pub struct Conv1D<
+    T,
+    const M: usize,
+    const N: usize,
+    const K: usize,
+    const C_IN: usize = 1,
+    const C_OUT: usize = 1,
+    const STRIDE: usize = 1,
+    const DILATION: usize = 1,
+    const PAD: usize = 0,
+> {
+    weights: [Matrix<C_IN, K>; C_OUT],
+    bias: Vector<C_OUT>,
     phantom: PhantomData<T>,
 }
 
-impl<T, const M: usize, const N: usize> std::ops::AddAssign<&Conv1D<T, M, N>> for Conv1D<T, M, N> {
-    fn add_assign(&mut self, rhs: &Conv1D<T, M, N>) {
-        self.weights += rhs.weights;
+impl<
+        T,
+        const M: usize,
+        const N: usize,
+        const K: usize,
+        const C_IN: usize,
+        const C_OUT: usize,
+        const STRIDE: usize,
+        const DILATION: usize,
+        const PAD: usize,
+    > std::ops::AddAssign<&Conv1D<T, M, N, K, C_IN, C_OUT, STRIDE, DILATION, PAD>>
+    for Conv1D<T, M, N, K, C_IN, C_OUT, STRIDE, DILATION, PAD>
+{
+    fn add_assign(&mut self, rhs: &Conv1D<T, M, N, K, C_IN, C_OUT, STRIDE, DILATION, PAD>) {
+        for (u, v) in self.weights.iter_mut().zip(rhs.weights.iter()) {
+            *u += v;
+        }
+
         self.bias += rhs.bias;
     }
 }
 
-impl<T, const M: usize, const N: usize> Conv1D<T, M, N> {
-    pub fn from_raw(weights: Vector<M>, bias: Vector<N>) -> Self {
-        Self { weights, bias, phantom: PhantomData }
+impl<
+        T,
+        const M: usize,
+        const N: usize,
+        const K: usize,
+        const C_IN: usize,
+        const C_OUT: usize,
+        const STRIDE: usize,
+        const DILATION: usize,
+        const PAD: usize,
+    > Conv1D<T, M, N, K, C_IN, C_OUT, STRIDE, DILATION, PAD>
+{
+    pub const INPUT_LEN: usize = M / C_IN;
+    pub const OUTPUT_LEN: usize = N / C_OUT;
+
+    const VALID_SHAPE: () = {
+        assert!(K > 0 && STRIDE > 0 && DILATION > 0);
+        assert!(
+            M.is_multiple_of(C_IN),
+            "input size must be a multiple of C_IN"
+        );
+        assert!(
+            N.is_multiple_of(C_OUT),
+            "output size must be a multiple of C_OUT"
+        );
+        assert!(
+            M / C_IN + 2 * PAD > DILATION * (K - 1),
+            "kernel larger than input"
+        );
+        assert!(
+            N / C_OUT == (M / C_IN + 2 * PAD - DILATION * (K - 1) - 1) / STRIDE + 1,
+            "output length does not match input length, kernel, stride, dilation and padding"
+        );
+    };
+
+    pub const fn from_raw(weights: [Matrix<C_IN, K>; C_OUT], bias: Vector<C_OUT>) -> Self {
+        let () = Self::VALID_SHAPE;
+
+        Self {
+            weights,
+            bias,
+            phantom: PhantomData,
+        }
     }
 
     pub const fn zeroed() -> Self {
-        Self { weights: Vector::zeroed(), bias: Vector::zeroed(), phantom: PhantomData }
+        Self::from_raw([Matrix::zeroed(); C_OUT], Vector::zeroed())
+    }
+
+    /// Builds the layer from `w(out_channel, in_channel, kernel_idx)`
+    /// and `b(out_channel)`.
+    pub fn from_fn<W: FnMut(usize, usize, usize) -> f32, B: FnMut(usize) -> f32>(
+        mut w: W,
+        b: B,
+    ) -> Self {
+        let mut weights = [Matrix::zeroed(); C_OUT];
+
+        for (o, kernel) in weights.iter_mut().enumerate() {
+            *kernel = Matrix::from_fn(|c, k| w(o, c, k));
+        }
+
+        Self::from_raw(weights, Vector::from_fn(b))
+    }
+
+    pub fn kernel(&self, out_channel: usize) -> &Matrix<C_IN, K> {
+        &self.weights[out_channel]
+    }
+
+    pub fn kernel_mut(&mut self, out_channel: usize) -> &mut Matrix<C_IN, K> {
+        &mut self.weights[out_channel]
+    }
+
+    pub fn bias(&self) -> Vector<C_OUT> {
+        self.bias
+    }
+
+    pub fn bias_mut(&mut self) -> &mut Vector<C_OUT> {
+        &mut self.bias
+    }
+
+    /// Position in the unpadded input read by output position `pos` at kernel index `k`.
+    fn input_pos(pos: usize, k: usize) -> Option<usize> {
+        (pos * STRIDE + k * DILATION)
+            .checked_sub(PAD)
+            .filter(|&i| i < Self::INPUT_LEN)
     }
 }
 
+/// Convolution output before (`pre`) and after (`out`) activation.
 pub struct Conv1DLayers<const N: usize> {
+    pre: Vector<N>,
     out: Vector<N>,
 }
 
@@ -38,61 +160,145 @@ impl<const N: usize> OutputLayer<Vector<N>> for Conv1DLayers<N> {
     }
 }
 
-impl<T, const M: usize, const N: usize> FeedForwardNetwork for Conv1D<T, M, N>
-where T: Activation
+impl<
+        T: Activation,
+        const M: usize,
+        const N: usize,
+        const K: usize,
+        const C_IN: usize,
+        const C_OUT: usize,
+        const STRIDE: usize,
+        const DILATION: usize,
+        const PAD: usize,
+    > FeedForwardNetwork for Conv1D<T, M, N, K, C_IN, C_OUT, STRIDE, DILATION, PAD>
 {
     type InputType = Vector<M>;
     type OutputType = Vector<N>;
     type Layers = Conv1DLayers<N>;
 
-    fn adam(&mut self, g: &Self, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
-        self.weights.adam(g.weights, &mut m.weights, &mut v.weights, adj, lr);
-        self.bias.adam(g.bias, &mut m.bias, &mut v.bias, adj, lr);
+    fn update<O: Optimizer>(&mut self, g: &Self, m: &mut Self, v: &mut Self, opt: &O, step: &Step) {
+        for o in 0..C_OUT {
+            self.weights[o].update(
+                &g.weights[o],
+                &mut m.weights[o],
+                &mut v.weights[o],
+                opt,
+                step,
+            );
+
+            if let Some((min, max)) = step.weight_clip {
+                self.weights[o].clamp(min, max);
+            }
+        }
+
+        self.bias
+            .update(&g.bias, &mut m.bias, &mut v.bias, opt, step);
+    }
+
+    fn layer_shapes(shapes: &mut Vec<LayerShape>) {
+        shapes.push(LayerShape::new::<Self>(
+            &format!("Conv1D<{}>", T::NAME),
+            &[M, N, K, C_IN, C_OUT, STRIDE, DILATION, PAD],
+        ));
+    }
+
+    fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        let () = Self::VALID_SHAPE;
+        let (len_in, len_out) = (Self::INPUT_LEN, Self::OUTPUT_LEN);
+
+        let pre = Vector::from_fn(|idx| {
+            let (o, pos) = (idx / len_out, idx % len_out);
+            let mut val = self.bias[o];
+
+            for k in 0..K {
+                if let Some(i) = Self::input_pos(pos, k) {
+                    for c in 0..C_IN {
+                        val += self.weights[o][c][k] * input[c * len_in + i];
+                    }
+                }
+            }
+
+            val
+        });
+
+        Self::Layers {
+            pre,
+            out: pre.activate::<T>(),
+        }
     }
 
     fn backprop(
         &self,
-        input: &Vector<M>,
+        input: &Self::InputType,
         grad: &mut Self,
-        mut out_err: Vector<N>,
-        layers: &Conv1DLayers<N>,
-    ) -> Vector<M> {
-        let k = M - N + 1;
-        out_err = out_err * layers.out.derivative::<T>();
+        mut out_err: Self::OutputType,
+        layers: &Self::Layers,
+    ) -> Self::InputType {
+        let (len_in, len_out) = (Self::INPUT_LEN, Self::OUTPUT_LEN);
+        out_err = out_err * layers.pre.derivative::<T>();
+
+        let mut in_err = Vector::zeroed();
 
-        grad.bias += out_err;
+        for o in 0..C_OUT {
+            for pos in 0..len_out {
+                let err = out_err[o * len_out + pos];
+                grad.bias[o] += err;
 
-        for i in 0..N {
-            for j in 0..k {
-                grad.weights[j] += out_err[i] * input[i + j];
+                for k in 0..K {
+                    if let Some(i) = Self::input_pos(pos, k) {
+                        for c in 0..C_IN {
+                            grad.weights[o][c][k] += err * input[c * len_in + i];
+                            in_err[c * len_in + i] += err * self.weights[o][c][k];
+                        }
+                    }
+                }
             }
         }
 
-        Vector::from_fn(|i| {
-            let mut val = 0.0;
-            for j in 0..k {
-                let elem = if i < k - 1 || i >= N + k - 1 {
-                    0.0
-                } else {
-                    out_err[i - k + 1] * self.weights[j]
-                };
-                val += elem;
-            }
-            val
-        })
+        in_err
     }
+}
 
-    fn out_with_layers(&self, input: &Vector<M>) -> Conv1DLayers<N> {
-        let k = M - N + 1;
+#[cfg(test)]
+mod test {
+    use super::{same_padding, Conv1D};
 
-        let out = Vector::from_fn(|i| {
-            let mut val = self.bias[i];
-            for j in 0..k {
-                val += input[i + j] * self.weights[j];
-            }
-            val
-        });
+    #[test]
+    fn conv1d() {
+        use goober_core::{activation::Identity, FeedForwardNetwork, Vector};
+
+        // two input channels of length 4, stride 2 and one zero of padding each end
+        let layer: Conv1D<Identity, 8, 2, 3, 2, 1, 2, 1, 1> =
+            Conv1D::from_fn(|_, c, k| (c * 3 + k) as f32, |_| 0.5);
+
+        let input = Vector::from_raw([1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+
+        // pos 0 reads [pad, 1, 2] and [pad, 5, 6], pos 1 reads [2, 3, 4] and [6, 7, 8]
+        let expected = Vector::from_raw([
+            0.5 + (1.0 + 2.0 * 2.0) + (4.0 * 5.0 + 5.0 * 6.0),
+            0.5 + (3.0 + 2.0 * 4.0) + (3.0 * 6.0 + 4.0 * 7.0 + 5.0 * 8.0),
+        ]);
+
+        assert_eq!(layer.out(&input), expected);
+
+        let same: Conv1D<Identity, 6, 6, 5, 1, 1, 1, 1, { same_padding(5, 1) }> =
+            Conv1D::from_fn(|_, _, k| if k == 2 { 1.0 } else { 0.0 }, |_| 0.0);
+        let input = Vector::from_raw([1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(same.out(&input), input);
+    }
+
+    #[test]
+    fn conv1d_gradients() {
+        use goober_core::{activation::Tanh, gradcheck, loss::Mse, Vector};
+
+        let mut layer: Conv1D<Tanh, 12, 6, 3, 2, 3, 2, 2, 1> = Conv1D::from_fn(
+            |o, c, k| ((o * 5 + c * 3 + k) as f32 * 0.77).sin() * 0.5,
+            |o| 0.1 * o as f32,
+        );
+        let input = Vector::from_fn(|i| (i as f32 * 1.3).cos());
+        let target = Vector::from_fn(|i| 0.1 * i as f32 - 0.2);
 
-        Conv1DLayers { out: out.activate::<T>() }
+        let worst = gradcheck(&mut layer, &input, &target, &Mse, 0.001).unwrap();
+        assert!(worst.error < 1e-2, "{worst}");
     }
 }
@@ -0,0 +1,248 @@
+use std::marker::PhantomData;
+
+use goober_core::{
+    activation::Activation, FeedForwardNetwork, LayerShape, Matrix, Optimizer, OutputLayer, Step,
+    Vector,
+};
+
+/// `C` channels of `H`x`W` planes, the input and output of 2D layers.
+pub type Planes<const C: usize, const H: usize, const W: usize> = [Matrix<H, W>; C];
+
+/// 2D Convolution with stride 1 and zero padding that keeps each plane at `H`x`W`.
+/// - `T` is the activation function used.
+/// - `C_IN` and `C_OUT` are the number of input and output channels.
+/// - `K` is the (odd) width and height of the kernel.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Conv2D<
+    T,
+    const C_IN: usize,
+    const C_OUT: usize,
+    const H: usize,
+    const W: usize,
+    const K: usize,
+> {
+    weights: [[Matrix<K, K>; C_IN]; C_OUT],
+    bias: Vector<C_OUT>,
+    phantom: PhantomData<T>,
+}
+
+impl<T, const C_IN: usize, const C_OUT: usize, const H: usize, const W: usize, const K: usize>
+    std::ops::AddAssign<&Conv2D<T, C_IN, C_OUT, H, W, K>> for Conv2D<T, C_IN, C_OUT, H, W, K>
+{
+    fn add_assign(&mut self, rhs: &Conv2D<T, C_IN, C_OUT, H, W, K>) {
+        for (u, v) in self.weights.iter_mut().zip(rhs.weights.iter()) {
+            for (a, b) in u.iter_mut().zip(v.iter()) {
+                *a += b;
+            }
+        }
+
+        self.bias += rhs.bias;
+    }
+}
+
+impl<T, const C_IN: usize, const C_OUT: usize, const H: usize, const W: usize, const K: usize>
+    Conv2D<T, C_IN, C_OUT, H, W, K>
+{
+    const VALID_SHAPE: () = assert!(K % 2 == 1, "kernel size must be odd");
+
+    pub const fn from_raw(weights: [[Matrix<K, K>; C_IN]; C_OUT], bias: Vector<C_OUT>) -> Self {
+        let () = Self::VALID_SHAPE;
+
+        Self {
+            weights,
+            bias,
+            phantom: PhantomData,
+        }
+    }
+
+    pub const fn zeroed() -> Self {
+        Self::from_raw([[Matrix::zeroed(); C_IN]; C_OUT], Vector::zeroed())
+    }
+
+    /// Builds the layer from `w(out_channel, in_channel, row, col)` and `b(out_channel)`.
+    pub fn from_fn<F: FnMut(usize, usize, usize, usize) -> f32, B: FnMut(usize) -> f32>(
+        mut w: F,
+        b: B,
+    ) -> Self {
+        let mut weights = [[Matrix::zeroed(); C_IN]; C_OUT];
+
+        for (o, kernels) in weights.iter_mut().enumerate() {
+            for (c, kernel) in kernels.iter_mut().enumerate() {
+                *kernel = Matrix::from_fn(|i, j| w(o, c, i, j));
+            }
+        }
+
+        Self::from_raw(weights, Vector::from_fn(b))
+    }
+
+    pub fn kernel(&self, out_channel: usize, in_channel: usize) -> &Matrix<K, K> {
+        &self.weights[out_channel][in_channel]
+    }
+
+    pub fn kernel_mut(&mut self, out_channel: usize, in_channel: usize) -> &mut Matrix<K, K> {
+        &mut self.weights[out_channel][in_channel]
+    }
+
+    /// Input position read at output position `pos` by kernel index `k`, along an axis of `len`.
+    fn input_pos(pos: usize, k: usize, len: usize) -> Option<usize> {
+        (pos + k).checked_sub(K / 2).filter(|&i| i < len)
+    }
+}
+
+/// Convolution output before (`pre`) and after (`out`) activation.
+pub struct Conv2DLayers<const C: usize, const H: usize, const W: usize> {
+    pre: Planes<C, H, W>,
+    out: Planes<C, H, W>,
+}
+
+impl<const C: usize, const H: usize, const W: usize> OutputLayer<Planes<C, H, W>>
+    for Conv2DLayers<C, H, W>
+{
+    fn output_layer(&self) -> Planes<C, H, W> {
+        self.out
+    }
+}
+
+impl<
+        T: Activation,
+        const C_IN: usize,
+        const C_OUT: usize,
+        const H: usize,
+        const W: usize,
+        const K: usize,
+    > FeedForwardNetwork for Conv2D<T, C_IN, C_OUT, H, W, K>
+{
+    type InputType = Planes<C_IN, H, W>;
+    type OutputType = Planes<C_OUT, H, W>;
+    type Layers = Conv2DLayers<C_OUT, H, W>;
+
+    fn update<O: Optimizer>(&mut self, g: &Self, m: &mut Self, v: &mut Self, opt: &O, step: &Step) {
+        for o in 0..C_OUT {
+            for c in 0..C_IN {
+                let kernel = &mut self.weights[o][c];
+                kernel.update(
+                    &g.weights[o][c],
+                    &mut m.weights[o][c],
+                    &mut v.weights[o][c],
+                    opt,
+                    step,
+                );
+
+                if let Some((min, max)) = step.weight_clip {
+                    kernel.clamp(min, max);
+                }
+            }
+        }
+
+        self.bias
+            .update(&g.bias, &mut m.bias, &mut v.bias, opt, step);
+    }
+
+    fn layer_shapes(shapes: &mut Vec<LayerShape>) {
+        shapes.push(LayerShape::new::<Self>(
+            &format!("Conv2D<{}>", T::NAME),
+            &[C_IN, C_OUT, H, W, K],
+        ));
+    }
+
+    fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        let () = Self::VALID_SHAPE;
+
+        let mut pre = [Matrix::zeroed(); C_OUT];
+
+        for (o, plane) in pre.iter_mut().enumerate() {
+            *plane = Matrix::from_fn(|i, j| {
+                let mut val = self.bias[o];
+
+                for (c, kernel) in self.weights[o].iter().enumerate() {
+                    for ki in 0..K {
+                        let Some(y) = Self::input_pos(i, ki, H) else {
+                            continue;
+                        };
+
+                        for kj in 0..K {
+                            if let Some(x) = Self::input_pos(j, kj, W) {
+                                val += kernel[ki][kj] * input[c][y][x];
+                            }
+                        }
+                    }
+                }
+
+                val
+            });
+        }
+
+        let mut out = pre;
+        for plane in out.iter_mut() {
+            for row in plane.iter_mut() {
+                *row = row.activate::<T>();
+            }
+        }
+
+        Self::Layers { pre, out }
+    }
+
+    fn backprop(
+        &self,
+        input: &Self::InputType,
+        grad: &mut Self,
+        mut out_err: Self::OutputType,
+        layers: &Self::Layers,
+    ) -> Self::InputType {
+        for (err, pre) in out_err.iter_mut().zip(layers.pre.iter()) {
+            for (err_row, pre_row) in err.iter_mut().zip(pre.iter()) {
+                *err_row = *err_row * pre_row.derivative::<T>();
+            }
+        }
+
+        let mut in_err = [Matrix::zeroed(); C_IN];
+
+        for (o, err) in out_err.iter().enumerate() {
+            for i in 0..H {
+                for j in 0..W {
+                    let e = err[i][j];
+                    grad.bias[o] += e;
+
+                    for c in 0..C_IN {
+                        for ki in 0..K {
+                            let Some(y) = Self::input_pos(i, ki, H) else {
+                                continue;
+                            };
+
+                            for kj in 0..K {
+                                if let Some(x) = Self::input_pos(j, kj, W) {
+                                    grad.weights[o][c][ki][kj] += e * input[c][y][x];
+                                    in_err[c][y][x] += e * self.weights[o][c][ki][kj];
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        in_err
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Conv2D;
+
+    #[test]
+    fn conv2d() {
+        use goober_core::{activation::Identity, FeedForwardNetwork, Matrix};
+
+        // 3x3 box sum
+        let layer: Conv2D<Identity, 1, 1, 3, 3, 3> = Conv2D::from_fn(|_, _, _, _| 1.0, |_| 0.0);
+        let input = [Matrix::from_fn(|_, _| 1.0)];
+
+        let expected = Matrix::from_fn(|i, j| {
+            let edges = |x| if x == 1 { 3.0 } else { 2.0 };
+            edges(i) * edges(j)
+        });
+
+        assert_eq!(layer.out(&input)[0], expected);
+    }
+}
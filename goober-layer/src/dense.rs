@@ -1,6 +1,9 @@
-use std::marker::PhantomData;
+use std::{borrow::Borrow, marker::PhantomData};
 
-use goober_core::{activation::Activation, FeedForwardNetwork, Matrix, OutputLayer, Vector};
+use goober_core::{
+    activation::Activation, FeedForwardNetwork, LayerShape, Matrix, Optimizer, OutputLayer, Step,
+    Vector,
+};
 
 /// Fully-Connected layer.
 /// - `T` is the activation function used.
@@ -64,8 +67,11 @@ impl<T: Activation, const M: usize, const N: usize> DenseConnected<T, M, N> {
     }
 }
 
+/// Cached output of the layer, along with its pre-activation values
+/// which are needed to evaluate the activation derivative in `backprop`.
 pub struct DenseConnectedLayers<const N: usize> {
-    out: Vector<N>,
+    pub(crate) pre: Vector<N>,
+    pub(crate) out: Vector<N>,
 }
 
 impl<const N: usize> OutputLayer<Vector<N>> for DenseConnectedLayers<N> {
@@ -79,16 +85,31 @@ impl<T: Activation, const M: usize, const N: usize> FeedForwardNetwork for Dense
     type OutputType = Vector<N>;
     type Layers = DenseConnectedLayers<N>;
 
-    fn adam(&mut self, g: &Self, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
+    fn update<O: Optimizer>(&mut self, g: &Self, m: &mut Self, v: &mut Self, opt: &O, step: &Step) {
         self.weights
-            .adam(&g.weights, &mut m.weights, &mut v.weights, adj, lr);
+            .update(&g.weights, &mut m.weights, &mut v.weights, opt, step);
 
-        self.bias.adam(g.bias, &mut m.bias, &mut v.bias, adj, lr);
+        if let Some((min, max)) = step.weight_clip {
+            self.weights.clamp(min, max);
+        }
+
+        self.bias
+            .update(&g.bias, &mut m.bias, &mut v.bias, opt, step);
+    }
+
+    fn layer_shapes(shapes: &mut Vec<LayerShape>) {
+        shapes.push(LayerShape::new::<Self>(
+            &format!("DenseConnected<{}>", T::NAME),
+            &[M, N],
+        ));
     }
 
     fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        let pre = self.weights.mul(input) + self.bias;
+
         Self::Layers {
-            out: (self.weights.mul(input) + self.bias).activate::<T>(),
+            pre,
+            out: pre.activate::<T>(),
         }
     }
 
@@ -99,7 +120,7 @@ impl<T: Activation, const M: usize, const N: usize> FeedForwardNetwork for Dense
         mut out_err: Self::OutputType,
         layers: &Self::Layers,
     ) -> Self::InputType {
-        out_err = out_err * layers.out.derivative::<T>();
+        out_err = out_err * layers.pre.derivative::<T>();
 
         for (i, row) in grad.weights.iter_mut().enumerate() {
             row.madd(&out_err, input[i]);
@@ -108,11 +129,47 @@ impl<T: Activation, const M: usize, const N: usize> FeedForwardNetwork for Dense
         grad.bias += out_err;
         self.weights.transpose_mul(&out_err)
     }
+
+    fn out_with_layers_batch<I: Borrow<Self::InputType>>(&self, inputs: &[I]) -> Vec<Self::Layers> {
+        self.weights
+            .mul_batch(inputs)
+            .into_iter()
+            .map(|sum| {
+                let pre = sum + self.bias;
+                Self::Layers {
+                    pre,
+                    out: pre.activate::<T>(),
+                }
+            })
+            .collect()
+    }
+
+    fn backprop_batch<I: Borrow<Self::InputType>, B: Borrow<Self::Layers>>(
+        &self,
+        inputs: &[I],
+        grad: &mut Self,
+        out_errs: Vec<Self::OutputType>,
+        layers: &[B],
+    ) -> Vec<Self::InputType> {
+        let out_errs = out_errs
+            .into_iter()
+            .zip(layers)
+            .map(|(err, layers)| err * layers.borrow().pre.derivative::<T>())
+            .collect::<Vec<_>>();
+
+        grad.weights.add_outer_batch(inputs, &out_errs);
+        for &err in &out_errs {
+            grad.bias += err;
+        }
+
+        self.weights.transpose_mul_batch(&out_errs)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::DenseConnected;
+    use goober_core::activation::Activation;
 
     #[test]
     fn dense_connected() {
@@ -143,4 +200,28 @@ mod test {
             assert_eq!(e, layer.out(i));
         }
     }
+
+    fn check_gradients<T: Activation>() {
+        use goober_core::{gradcheck, loss::Mse, Vector};
+
+        let mut layer: DenseConnected<T, 3, 2> = DenseConnected::from_fn(
+            |i, j| 0.3 * i as f32 - 0.2 * j as f32 + 0.1,
+            |j| 0.25 - 0.5 * j as f32,
+        );
+        let input = Vector::from_raw([0.4, -0.3, 0.9]);
+        let target = Vector::from_raw([1.0, -2.0]);
+
+        let worst = gradcheck(&mut layer, &input, &target, &Mse, 0.001).unwrap();
+        assert!(worst.error < 1e-2, "{worst}");
+    }
+
+    #[test]
+    fn dense_connected_gradients() {
+        use goober_core::activation::{Identity, ReLU, SCReLU, Tanh};
+
+        check_gradients::<Identity>();
+        check_gradients::<ReLU>();
+        check_gradients::<SCReLU>();
+        check_gradients::<Tanh>();
+    }
 }
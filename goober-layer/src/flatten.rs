@@ -0,0 +1,66 @@
+use goober_core::{FeedForwardNetwork, LayerShape, Matrix, Optimizer, OutputLayer, Step, Vector};
+
+use crate::conv2d::Planes;
+
+/// Flattens `C` planes of `H`x`W` into a vector of size `N = C * H * W`,
+/// plane by plane and row by row.
+#[derive(Clone, Copy, Default)]
+pub struct Flatten<const C: usize, const H: usize, const W: usize, const N: usize>;
+
+impl<const C: usize, const H: usize, const W: usize, const N: usize>
+    std::ops::AddAssign<&Flatten<C, H, W, N>> for Flatten<C, H, W, N>
+{
+    fn add_assign(&mut self, _: &Flatten<C, H, W, N>) {}
+}
+
+impl<const C: usize, const H: usize, const W: usize, const N: usize> Flatten<C, H, W, N> {
+    const VALID_SHAPE: () = assert!(N == C * H * W, "flattened size must be C * H * W");
+}
+
+pub struct FlattenLayers<const N: usize> {
+    out: Vector<N>,
+}
+
+impl<const N: usize> OutputLayer<Vector<N>> for FlattenLayers<N> {
+    fn output_layer(&self) -> Vector<N> {
+        self.out
+    }
+}
+
+impl<const C: usize, const H: usize, const W: usize, const N: usize> FeedForwardNetwork
+    for Flatten<C, H, W, N>
+{
+    type InputType = Planes<C, H, W>;
+    type OutputType = Vector<N>;
+    type Layers = FlattenLayers<N>;
+
+    fn update<O: Optimizer>(&mut self, _: &Self, _: &mut Self, _: &mut Self, _: &O, _: &Step) {}
+
+    fn layer_shapes(shapes: &mut Vec<LayerShape>) {
+        shapes.push(LayerShape::new::<Self>("Flatten", &[C, H, W, N]));
+    }
+
+    fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        let () = Self::VALID_SHAPE;
+
+        Self::Layers {
+            out: Vector::from_fn(|idx| input[idx / (H * W)][idx / W % H][idx % W]),
+        }
+    }
+
+    fn backprop(
+        &self,
+        _: &Self::InputType,
+        _: &mut Self,
+        out_err: Self::OutputType,
+        _: &Self::Layers,
+    ) -> Self::InputType {
+        let mut in_err = [Matrix::zeroed(); C];
+
+        for (c, plane) in in_err.iter_mut().enumerate() {
+            *plane = Matrix::from_fn(|i, j| out_err[c * H * W + i * W + j]);
+        }
+
+        in_err
+    }
+}
@@ -1,9 +1,27 @@
+mod accumulator;
+mod activate;
 mod add;
+mod concat;
 mod conv1d;
+mod conv2d;
 mod dense;
+mod flatten;
+mod parametric;
+mod perspective;
+mod pool;
+mod quantised;
 mod sparse;
 
+pub use accumulator::Accumulator;
+pub use activate::{Activate, LogSoftmax, Softmax};
 pub use add::Add;
-pub use conv1d::Conv1D;
+pub use concat::Concat;
+pub use conv1d::{same_padding, Conv1D};
+pub use conv2d::{Conv2D, Planes};
 pub use dense::DenseConnected;
+pub use flatten::Flatten;
+pub use parametric::{LearnableCReLU, PReLU};
+pub use perspective::Perspective;
+pub use pool::{AvgPool2D, MaxPool2D};
+pub use quantised::{QuantisedDense, QuantisedPerspective, QuantisedSparse};
 pub use sparse::SparseConnected;
@@ -0,0 +1,184 @@
+use goober_core::{FeedForwardNetwork, LayerShape, Optimizer, OutputLayer, Step, Vector};
+
+/// Parametric ReLU, with a learnable slope for negative inputs for each of `N` neurons.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct PReLU<const N: usize> {
+    slopes: Vector<N>,
+}
+
+/// Clipped ReLU with a learnable ceiling for each of `N` neurons,
+/// clamping each input `x` to `[0, ceiling]`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LearnableCReLU<const N: usize> {
+    ceilings: Vector<N>,
+}
+
+impl<const N: usize> std::ops::AddAssign<&PReLU<N>> for PReLU<N> {
+    fn add_assign(&mut self, rhs: &PReLU<N>) {
+        self.slopes += rhs.slopes;
+    }
+}
+
+impl<const N: usize> std::ops::AddAssign<&LearnableCReLU<N>> for LearnableCReLU<N> {
+    fn add_assign(&mut self, rhs: &LearnableCReLU<N>) {
+        self.ceilings += rhs.ceilings;
+    }
+}
+
+impl<const N: usize> PReLU<N> {
+    pub const fn from_raw(slopes: Vector<N>) -> Self {
+        Self { slopes }
+    }
+
+    pub const fn zeroed() -> Self {
+        Self::from_raw(Vector::zeroed())
+    }
+
+    pub fn slopes(&self) -> Vector<N> {
+        self.slopes
+    }
+
+    pub fn slopes_mut(&mut self) -> &mut Vector<N> {
+        &mut self.slopes
+    }
+}
+
+impl<const N: usize> LearnableCReLU<N> {
+    pub const fn from_raw(ceilings: Vector<N>) -> Self {
+        Self { ceilings }
+    }
+
+    pub const fn zeroed() -> Self {
+        Self::from_raw(Vector::zeroed())
+    }
+
+    pub fn ceilings(&self) -> Vector<N> {
+        self.ceilings
+    }
+
+    pub fn ceilings_mut(&mut self) -> &mut Vector<N> {
+        &mut self.ceilings
+    }
+}
+
+pub struct ParametricLayers<const N: usize> {
+    out: Vector<N>,
+}
+
+impl<const N: usize> OutputLayer<Vector<N>> for ParametricLayers<N> {
+    fn output_layer(&self) -> Vector<N> {
+        self.out
+    }
+}
+
+impl<const N: usize> FeedForwardNetwork for PReLU<N> {
+    type InputType = Vector<N>;
+    type OutputType = Vector<N>;
+    type Layers = ParametricLayers<N>;
+
+    fn update<O: Optimizer>(&mut self, g: &Self, m: &mut Self, v: &mut Self, opt: &O, step: &Step) {
+        self.slopes
+            .update(&g.slopes, &mut m.slopes, &mut v.slopes, opt, step);
+    }
+
+    fn layer_shapes(shapes: &mut Vec<LayerShape>) {
+        shapes.push(LayerShape::new::<Self>("PReLU", &[N]));
+    }
+
+    fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        Self::Layers {
+            out: Vector::from_fn(|i| {
+                if input[i] > 0.0 {
+                    input[i]
+                } else {
+                    self.slopes[i] * input[i]
+                }
+            }),
+        }
+    }
+
+    fn backprop(
+        &self,
+        input: &Self::InputType,
+        grad: &mut Self,
+        out_err: Self::OutputType,
+        _: &Self::Layers,
+    ) -> Self::InputType {
+        Vector::from_fn(|i| {
+            if input[i] > 0.0 {
+                out_err[i]
+            } else {
+                grad.slopes[i] += out_err[i] * input[i];
+                out_err[i] * self.slopes[i]
+            }
+        })
+    }
+}
+
+impl<const N: usize> FeedForwardNetwork for LearnableCReLU<N> {
+    type InputType = Vector<N>;
+    type OutputType = Vector<N>;
+    type Layers = ParametricLayers<N>;
+
+    fn update<O: Optimizer>(&mut self, g: &Self, m: &mut Self, v: &mut Self, opt: &O, step: &Step) {
+        self.ceilings
+            .update(&g.ceilings, &mut m.ceilings, &mut v.ceilings, opt, step);
+    }
+
+    fn layer_shapes(shapes: &mut Vec<LayerShape>) {
+        shapes.push(LayerShape::new::<Self>("LearnableCReLU", &[N]));
+    }
+
+    fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        Self::Layers {
+            out: Vector::from_fn(|i| input[i].max(0.0).min(self.ceilings[i])),
+        }
+    }
+
+    fn backprop(
+        &self,
+        input: &Self::InputType,
+        grad: &mut Self,
+        out_err: Self::OutputType,
+        _: &Self::Layers,
+    ) -> Self::InputType {
+        Vector::from_fn(|i| {
+            if input[i].max(0.0) >= self.ceilings[i] {
+                grad.ceilings[i] += out_err[i];
+                0.0
+            } else if input[i] > 0.0 {
+                out_err[i]
+            } else {
+                0.0
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LearnableCReLU, PReLU};
+
+    #[test]
+    fn parametric_gradients() {
+        use goober_core::{gradcheck, loss::Mse, Vector};
+
+        let input = Vector::from_raw([-1.5, -0.2, 0.3, 0.9, 2.0]);
+        let target = Vector::from_raw([0.5, -0.5, 0.0, 1.0, 0.2]);
+
+        let mut prelu = PReLU::from_raw(Vector::from_raw([0.25, 0.1, 0.5, 0.3, 0.2]));
+        let worst = gradcheck(&mut prelu, &input, &target, &Mse, 0.001).unwrap();
+        assert!(worst.error < 1e-2, "{worst}");
+
+        let mut crelu = LearnableCReLU::from_raw(Vector::from_raw([1.0, 1.0, 0.5, 0.5, 1.5]));
+        let worst = gradcheck(&mut crelu, &input, &target, &Mse, 0.001).unwrap();
+        assert!(worst.error < 1e-2, "{worst}");
+
+        // negative ceilings clamp every input, including those below them
+        let mut crelu = LearnableCReLU::from_raw(Vector::from_raw([-0.5, -1.0, -0.2, 0.5, 1.5]));
+        let worst = gradcheck(&mut crelu, &input, &target, &Mse, 0.001).unwrap();
+        assert!(worst.error < 1e-2, "{worst}");
+    }
+}
@@ -0,0 +1,110 @@
+use goober_core::{
+    FeedForwardNetwork, LayerShape, LazyUpdate, Optimizer, OutputLayer, Step, TouchedRows, Vector,
+};
+
+use crate::concat::{concat, split};
+
+/// Applies one sub-network, usually a [`crate::SparseConnected`] feature
+/// transformer, to a pair of inputs with shared weights and concatenates both
+/// outputs in input order, e.g. side-to-move then opponent.
+/// `N` must be twice the output size of `L`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Perspective<L, const N: usize> {
+    inner: L,
+}
+
+impl<L, const N: usize> std::ops::AddAssign<&Perspective<L, N>> for Perspective<L, N>
+where
+    for<'a> L: FeedForwardNetwork + std::ops::AddAssign<&'a L>,
+{
+    fn add_assign(&mut self, rhs: &Perspective<L, N>) {
+        self.inner += &rhs.inner;
+    }
+}
+
+pub struct PerspectiveLayers<L: FeedForwardNetwork, const N: usize> {
+    pub(crate) us: L::Layers,
+    pub(crate) them: L::Layers,
+}
+
+impl<L, const NL: usize, const N: usize> OutputLayer<Vector<N>> for PerspectiveLayers<L, N>
+where
+    L: FeedForwardNetwork<OutputType = Vector<NL>>,
+{
+    fn output_layer(&self) -> Vector<N> {
+        concat::<NL, NL, N>(&self.us.output_layer(), &self.them.output_layer())
+    }
+}
+
+impl<L, const NL: usize, const N: usize> FeedForwardNetwork for Perspective<L, N>
+where
+    L: FeedForwardNetwork<OutputType = Vector<NL>>,
+{
+    type InputType = (L::InputType, L::InputType);
+    type OutputType = Vector<N>;
+    type Layers = PerspectiveLayers<L, N>;
+
+    const LAZY: bool = L::LAZY;
+
+    fn update<O: Optimizer>(&mut self, g: &Self, m: &mut Self, v: &mut Self, opt: &O, step: &Step) {
+        self.inner
+            .update(&g.inner, &mut m.inner, &mut v.inner, opt, step);
+    }
+
+    fn layer_shapes(shapes: &mut Vec<LayerShape>) {
+        let start = shapes.len();
+        L::layer_shapes(shapes);
+        for shape in &mut shapes[start..] {
+            shape.nest("inner", std::mem::offset_of!(Self, inner));
+        }
+    }
+
+    fn touched_rows(input: &Self::InputType, rows: &mut TouchedRows) {
+        L::touched_rows(&input.0, rows);
+        L::touched_rows(&input.1, rows);
+    }
+
+    fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        Self::Layers {
+            us: self.inner.out_with_layers(&input.0),
+            them: self.inner.out_with_layers(&input.1),
+        }
+    }
+
+    fn backprop(
+        &self,
+        input: &Self::InputType,
+        grad: &mut Self,
+        out_err: Self::OutputType,
+        layers: &Self::Layers,
+    ) -> Self::InputType {
+        let (us_err, them_err) = split::<NL, NL, N>(&out_err);
+        let us_back = self
+            .inner
+            .backprop(&input.0, &mut grad.inner, us_err, &layers.us);
+        let them_back = self
+            .inner
+            .backprop(&input.1, &mut grad.inner, them_err, &layers.them);
+        (us_back, them_back)
+    }
+}
+
+impl<L, const NL: usize, const N: usize> LazyUpdate for Perspective<L, N> where
+    L: LazyUpdate<OutputType = Vector<NL>>
+{
+}
+
+impl<L, const N: usize> Perspective<L, N> {
+    pub const fn from_raw(inner: L) -> Self {
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut L {
+        &mut self.inner
+    }
+}
@@ -0,0 +1,203 @@
+use goober_core::{FeedForwardNetwork, LayerShape, Matrix, Optimizer, OutputLayer, Step};
+
+use crate::conv2d::Planes;
+
+/// Takes the maximum over non-overlapping windows, reducing each of
+/// `C` planes from `H`x`W` to `OH`x`OW`.
+#[derive(Clone, Copy, Default)]
+pub struct MaxPool2D<
+    const C: usize,
+    const H: usize,
+    const W: usize,
+    const OH: usize,
+    const OW: usize,
+>;
+
+/// Takes the mean over non-overlapping windows, reducing each of
+/// `C` planes from `H`x`W` to `OH`x`OW`.
+#[derive(Clone, Copy, Default)]
+pub struct AvgPool2D<
+    const C: usize,
+    const H: usize,
+    const W: usize,
+    const OH: usize,
+    const OW: usize,
+>;
+
+pub struct PoolLayers<const C: usize, const H: usize, const W: usize> {
+    out: Planes<C, H, W>,
+}
+
+impl<const C: usize, const H: usize, const W: usize> OutputLayer<Planes<C, H, W>>
+    for PoolLayers<C, H, W>
+{
+    fn output_layer(&self) -> Planes<C, H, W> {
+        self.out
+    }
+}
+
+/// Window size of a pooling layer, checking that it evenly divides each plane.
+const fn window(h: usize, w: usize, oh: usize, ow: usize) -> (usize, usize) {
+    assert!(
+        oh > 0 && ow > 0 && h.is_multiple_of(oh) && w.is_multiple_of(ow),
+        "pool must evenly divide planes"
+    );
+    (h / oh, w / ow)
+}
+
+macro_rules! impl_pool {
+    ($name:ident, $kind:literal) => {
+        impl<const C: usize, const H: usize, const W: usize, const OH: usize, const OW: usize>
+            std::ops::AddAssign<&$name<C, H, W, OH, OW>> for $name<C, H, W, OH, OW>
+        {
+            fn add_assign(&mut self, _: &$name<C, H, W, OH, OW>) {}
+        }
+
+        impl<const C: usize, const H: usize, const W: usize, const OH: usize, const OW: usize>
+            $name<C, H, W, OH, OW>
+        {
+            const WINDOW: (usize, usize) = window(H, W, OH, OW);
+        }
+
+        impl<const C: usize, const H: usize, const W: usize, const OH: usize, const OW: usize>
+            FeedForwardNetwork for $name<C, H, W, OH, OW>
+        {
+            type InputType = Planes<C, H, W>;
+            type OutputType = Planes<C, OH, OW>;
+            type Layers = PoolLayers<C, OH, OW>;
+
+            fn update<O: Optimizer>(
+                &mut self,
+                _: &Self,
+                _: &mut Self,
+                _: &mut Self,
+                _: &O,
+                _: &Step,
+            ) {
+            }
+
+            fn layer_shapes(shapes: &mut Vec<LayerShape>) {
+                shapes.push(LayerShape::new::<Self>($kind, &[C, H, W, OH, OW]));
+            }
+
+            fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+                let mut out = [Matrix::zeroed(); C];
+
+                for (plane, inp) in out.iter_mut().zip(input.iter()) {
+                    *plane = Matrix::from_fn(|i, j| Self::pool(inp, i, j));
+                }
+
+                Self::Layers { out }
+            }
+
+            fn backprop(
+                &self,
+                input: &Self::InputType,
+                _: &mut Self,
+                out_err: Self::OutputType,
+                _: &Self::Layers,
+            ) -> Self::InputType {
+                let mut in_err = [Matrix::zeroed(); C];
+
+                for c in 0..C {
+                    for i in 0..OH {
+                        for j in 0..OW {
+                            Self::unpool(&input[c], &mut in_err[c], i, j, out_err[c][i][j]);
+                        }
+                    }
+                }
+
+                in_err
+            }
+        }
+    };
+}
+
+impl_pool!(MaxPool2D, "MaxPool2D");
+impl_pool!(AvgPool2D, "AvgPool2D");
+
+impl<const C: usize, const H: usize, const W: usize, const OH: usize, const OW: usize>
+    MaxPool2D<C, H, W, OH, OW>
+{
+    /// Position of the (first) maximum within window `(i, j)`.
+    fn argmax(input: &Matrix<H, W>, i: usize, j: usize) -> (usize, usize) {
+        let (wh, ww) = Self::WINDOW;
+        let mut best = (i * wh, j * ww);
+
+        for y in i * wh..(i + 1) * wh {
+            for x in j * ww..(j + 1) * ww {
+                if input[y][x] > input[best.0][best.1] {
+                    best = (y, x);
+                }
+            }
+        }
+
+        best
+    }
+
+    fn pool(input: &Matrix<H, W>, i: usize, j: usize) -> f32 {
+        let (y, x) = Self::argmax(input, i, j);
+        input[y][x]
+    }
+
+    fn unpool(input: &Matrix<H, W>, in_err: &mut Matrix<H, W>, i: usize, j: usize, err: f32) {
+        let (y, x) = Self::argmax(input, i, j);
+        in_err[y][x] += err;
+    }
+}
+
+impl<const C: usize, const H: usize, const W: usize, const OH: usize, const OW: usize>
+    AvgPool2D<C, H, W, OH, OW>
+{
+    fn pool(input: &Matrix<H, W>, i: usize, j: usize) -> f32 {
+        let (wh, ww) = Self::WINDOW;
+        let mut sum = 0.0;
+
+        for y in i * wh..(i + 1) * wh {
+            for x in j * ww..(j + 1) * ww {
+                sum += input[y][x];
+            }
+        }
+
+        sum / (wh * ww) as f32
+    }
+
+    fn unpool(_: &Matrix<H, W>, in_err: &mut Matrix<H, W>, i: usize, j: usize, err: f32) {
+        let (wh, ww) = Self::WINDOW;
+        let share = err / (wh * ww) as f32;
+
+        for y in i * wh..(i + 1) * wh {
+            for x in j * ww..(j + 1) * ww {
+                in_err[y][x] += share;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AvgPool2D, MaxPool2D};
+
+    #[test]
+    fn pool() {
+        use goober_core::{FeedForwardNetwork, Matrix};
+
+        let input = [Matrix::<4, 4>::from_fn(|i, j| (i * 4 + j) as f32)];
+
+        let max = MaxPool2D::<1, 4, 4, 2, 2>.out(&input);
+        assert_eq!(max[0], Matrix::from_fn(|i, j| (i * 8 + j * 2 + 5) as f32));
+
+        let avg = AvgPool2D::<1, 4, 4, 2, 2>.out(&input);
+        assert_eq!(avg[0], Matrix::from_fn(|i, j| (i * 8 + j * 2) as f32 + 2.5));
+
+        let err = [Matrix::from_fn(|_, _| 1.0)];
+        let back = MaxPool2D::<1, 4, 4, 2, 2>.backprop(
+            &input,
+            &mut MaxPool2D,
+            err,
+            &MaxPool2D.out_with_layers(&input),
+        );
+        assert_eq!(back[0][1][1], 1.0);
+        assert_eq!(back[0][0][0], 0.0);
+    }
+}
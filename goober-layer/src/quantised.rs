@@ -0,0 +1,304 @@
+use std::marker::PhantomData;
+
+use goober_core::{
+    quantise::{
+        Quantise, QuantiseError, QuantisedActivation, QuantisedNetwork, Quantiser, Rounding, Scales,
+    },
+    SparseVector, Vector,
+};
+
+use crate::{
+    concat::Lengths, dense::DenseConnectedLayers, perspective::PerspectiveLayers,
+    sparse::SparseConnectedLayers, DenseConnected, Perspective, SparseConnected,
+};
+
+/// [`DenseConnected`] with `i8` weights, `i32` biases and `i64` sums.
+#[derive(Clone)]
+pub struct QuantisedDense<T, const M: usize, const N: usize> {
+    weights: Vec<[i8; N]>,
+    bias: [i32; N],
+    sum_scale: i32,
+    output_scale: i32,
+    rounding: Rounding,
+    phantom: PhantomData<T>,
+}
+
+impl<T: QuantisedActivation, const M: usize, const N: usize> QuantisedNetwork
+    for QuantisedDense<T, M, N>
+{
+    type InputType = [i32; M];
+    type OutputType = [i32; N];
+
+    fn out(&self, input: &Self::InputType) -> Self::OutputType {
+        let mut sums = self.bias.map(i64::from);
+
+        for (&x, row) in input.iter().zip(self.weights.iter()) {
+            for (sum, &w) in sums.iter_mut().zip(row.iter()) {
+                *sum += i64::from(x) * i64::from(w);
+            }
+        }
+
+        sums.map(|sum| {
+            T::requantise(
+                sum,
+                i64::from(self.sum_scale),
+                i64::from(self.output_scale),
+                self.rounding,
+            )
+        })
+    }
+
+    fn output_scale(&self) -> i32 {
+        self.output_scale
+    }
+}
+
+impl<T: QuantisedActivation, const M: usize, const N: usize> Quantise for DenseConnected<T, M, N> {
+    type Quantised = QuantisedDense<T, M, N>;
+
+    fn quantise(
+        &self,
+        q: &Quantiser,
+        input_scale: i32,
+        scales: Scales,
+    ) -> Result<Self::Quantised, QuantiseError> {
+        let weight_scale = scales.weights()?;
+        let sum_scale = scales.sum(input_scale)?;
+        let bias = self.bias();
+
+        let mut weights = vec![[0; N]; M];
+        for (i, row) in weights.iter_mut().enumerate() {
+            let col = self.weights_col(i);
+            for (j, w) in row.iter_mut().enumerate() {
+                *w = q.quantise(col[j], weight_scale)?;
+            }
+        }
+
+        let mut quantised_bias = [0; N];
+        for (j, b) in quantised_bias.iter_mut().enumerate() {
+            *b = q.quantise(bias[j], sum_scale)?;
+        }
+
+        Ok(QuantisedDense {
+            weights,
+            bias: quantised_bias,
+            sum_scale,
+            output_scale: scales.output.unwrap_or(sum_scale),
+            rounding: q.rounding,
+            phantom: PhantomData,
+        })
+    }
+
+    fn out_with_layers_fake_quantised(
+        &self,
+        input: &Vector<M>,
+        q: &Quantiser,
+        input_scale: i32,
+        scales: Scales,
+    ) -> Result<(Self::Layers, i32), QuantiseError> {
+        let weight_scale = scales.weights()?;
+        let sum_scale = scales.sum(input_scale)?;
+        let output_scale = scales.output.unwrap_or(sum_scale);
+        let bias = self.bias();
+
+        // the quantised network rounds its input just as `quantise_input` does
+        let input = Vector::<M>::from_fn(|i| q.fake_quantise::<i32>(input[i], input_scale));
+
+        let mut pre = Vector::from_fn(|j| q.fake_quantise::<i32>(bias[j], sum_scale));
+        for i in 0..M {
+            let col = self.weights_col(i);
+            for j in 0..N {
+                pre[j] += input[i] * q.fake_quantise::<i8>(col[j], weight_scale);
+            }
+        }
+
+        let out = Vector::from_fn(|j| q.fake_quantise::<i32>(T::activate(pre[j]), output_scale));
+        Ok((DenseConnectedLayers { pre, out }, output_scale))
+    }
+
+    fn quantise_input(q: &Quantiser, input: &Vector<M>, input_scale: i32) -> [i32; M] {
+        std::array::from_fn(|i| q.saturating(input[i], input_scale))
+    }
+}
+
+/// [`SparseConnected`] with `i16` weights and biases.
+#[derive(Clone)]
+pub struct QuantisedSparse<T, const M: usize, const N: usize> {
+    weights: Vec<[i16; N]>,
+    bias: [i16; N],
+    sum_scale: i32,
+    output_scale: i32,
+    rounding: Rounding,
+    phantom: PhantomData<T>,
+}
+
+impl<T: QuantisedActivation, const M: usize, const N: usize> QuantisedNetwork
+    for QuantisedSparse<T, M, N>
+{
+    type InputType = SparseVector;
+    type OutputType = [i32; N];
+
+    fn out(&self, input: &Self::InputType) -> Self::OutputType {
+        let mut sums = self.bias.map(i32::from);
+
+        for &feat in input.iter() {
+            for (sum, &w) in sums.iter_mut().zip(self.weights[feat].iter()) {
+                *sum += i32::from(w);
+            }
+        }
+
+        sums.map(|sum| {
+            T::requantise(
+                i64::from(sum),
+                i64::from(self.sum_scale),
+                i64::from(self.output_scale),
+                self.rounding,
+            )
+        })
+    }
+
+    fn output_scale(&self) -> i32 {
+        self.output_scale
+    }
+}
+
+/// Sparse inputs are binary, so `input_scale` is ignored and
+/// the layer's sums have the scale of its weights.
+impl<T: QuantisedActivation, const M: usize, const N: usize> Quantise for SparseConnected<T, M, N> {
+    type Quantised = QuantisedSparse<T, M, N>;
+
+    fn quantise(
+        &self,
+        q: &Quantiser,
+        _: i32,
+        scales: Scales,
+    ) -> Result<Self::Quantised, QuantiseError> {
+        let sum_scale = scales.weights()?;
+        let bias = self.bias();
+
+        let mut weights = vec![[0; N]; M];
+        for (i, row) in weights.iter_mut().enumerate() {
+            let weights_row = self.weights_row(i);
+            for (j, w) in row.iter_mut().enumerate() {
+                *w = q.quantise(weights_row[j], sum_scale)?;
+            }
+        }
+
+        let mut quantised_bias = [0; N];
+        for (j, b) in quantised_bias.iter_mut().enumerate() {
+            *b = q.quantise(bias[j], sum_scale)?;
+        }
+
+        Ok(QuantisedSparse {
+            weights,
+            bias: quantised_bias,
+            sum_scale,
+            output_scale: scales.output.unwrap_or(sum_scale),
+            rounding: q.rounding,
+            phantom: PhantomData,
+        })
+    }
+
+    fn out_with_layers_fake_quantised(
+        &self,
+        input: &SparseVector,
+        q: &Quantiser,
+        _: i32,
+        scales: Scales,
+    ) -> Result<(Self::Layers, i32), QuantiseError> {
+        let sum_scale = scales.weights()?;
+        let output_scale = scales.output.unwrap_or(sum_scale);
+        let bias = self.bias();
+
+        let mut pre = Vector::from_fn(|j| q.fake_quantise::<i16>(bias[j], sum_scale));
+        for &feat in input.iter() {
+            let row = self.weights_row(feat);
+            for j in 0..N {
+                pre[j] += q.fake_quantise::<i16>(row[j], sum_scale);
+            }
+        }
+
+        let out = Vector::from_fn(|j| q.fake_quantise::<i32>(T::activate(pre[j]), output_scale));
+        Ok((SparseConnectedLayers { pre, out }, output_scale))
+    }
+
+    fn quantise_input(_: &Quantiser, input: &SparseVector, _: i32) -> SparseVector {
+        input.clone()
+    }
+}
+
+/// Quantised [`Perspective`].
+#[derive(Clone)]
+pub struct QuantisedPerspective<Q, const N: usize> {
+    inner: Q,
+}
+
+impl<Q, const NL: usize, const N: usize> QuantisedNetwork for QuantisedPerspective<Q, N>
+where
+    Q: QuantisedNetwork<OutputType = [i32; NL]>,
+{
+    type InputType = (Q::InputType, Q::InputType);
+    type OutputType = [i32; N];
+
+    fn out(&self, input: &Self::InputType) -> Self::OutputType {
+        let () = Lengths::<NL, NL, N>::VALID;
+        let us = self.inner.out(&input.0);
+        let them = self.inner.out(&input.1);
+        std::array::from_fn(|i| if i < NL { us[i] } else { them[i - NL] })
+    }
+
+    fn output_scale(&self) -> i32 {
+        self.inner.output_scale()
+    }
+}
+
+impl<L, const NL: usize, const N: usize> Quantise for Perspective<L, N>
+where
+    L: Quantise<OutputType = Vector<NL>>,
+    L::Quantised: QuantisedNetwork<OutputType = [i32; NL]>,
+{
+    type Quantised = QuantisedPerspective<L::Quantised, N>;
+
+    fn quantise(
+        &self,
+        q: &Quantiser,
+        input_scale: i32,
+        scales: Scales,
+    ) -> Result<Self::Quantised, QuantiseError> {
+        let inner = self
+            .inner()
+            .quantise(q, input_scale, scales)
+            .map_err(|err| err.nest("inner"))?;
+
+        Ok(QuantisedPerspective { inner })
+    }
+
+    fn out_with_layers_fake_quantised(
+        &self,
+        input: &Self::InputType,
+        q: &Quantiser,
+        input_scale: i32,
+        scales: Scales,
+    ) -> Result<(Self::Layers, i32), QuantiseError> {
+        let fake_quantised = |input| {
+            self.inner()
+                .out_with_layers_fake_quantised(input, q, input_scale, scales)
+                .map_err(|err| err.nest("inner"))
+        };
+
+        let (us, scale) = fake_quantised(&input.0)?;
+        let (them, _) = fake_quantised(&input.1)?;
+        Ok((PerspectiveLayers { us, them }, scale))
+    }
+
+    fn quantise_input(
+        q: &Quantiser,
+        input: &Self::InputType,
+        input_scale: i32,
+    ) -> <Self::Quantised as QuantisedNetwork>::InputType {
+        (
+            L::quantise_input(q, &input.0, input_scale),
+            L::quantise_input(q, &input.1, input_scale),
+        )
+    }
+}
@@ -1,7 +1,8 @@
 use std::marker::PhantomData;
 
 use goober_core::{
-    activation::Activation, FeedForwardNetwork, Matrix, OutputLayer, SparseVector, Vector,
+    activation::Activation, FeedForwardNetwork, LayerShape, LazyUpdate, Matrix, Optimizer,
+    OutputLayer, SparseVector, Step, TouchedRows, Vector,
 };
 
 /// Fully-Connected layer with sparse input.
@@ -63,8 +64,10 @@ impl<T: Activation, const M: usize, const N: usize> SparseConnected<T, M, N> {
     }
 }
 
+/// Accumulated sums before (`pre`) and after (`out`) activation.
 pub struct SparseConnectedLayers<const N: usize> {
-    out: Vector<N>,
+    pub(crate) pre: Vector<N>,
+    pub(crate) out: Vector<N>,
 }
 
 impl<const N: usize> OutputLayer<Vector<N>> for SparseConnectedLayers<N> {
@@ -80,17 +83,62 @@ impl<T: Activation, const M: usize, const N: usize> FeedForwardNetwork
     type OutputType = Vector<N>;
     type Layers = SparseConnectedLayers<N>;
 
-    fn adam(&mut self, grad: &Self, momentum: &mut Self, velocity: &mut Self, adj: f32, lr: f32) {
-        self.weights.adam(
-            &grad.weights,
-            &mut momentum.weights,
-            &mut velocity.weights,
-            adj,
-            lr,
+    fn update<O: Optimizer>(
+        &mut self,
+        grad: &Self,
+        momentum: &mut Self,
+        velocity: &mut Self,
+        opt: &O,
+        step: &Step,
+    ) {
+        if let Some(rows) = step.lazy_rows() {
+            for (i, row_step) in rows {
+                self.weights[i].update(
+                    &grad.weights[i],
+                    &mut momentum.weights[i],
+                    &mut velocity.weights[i],
+                    opt,
+                    &row_step,
+                );
+
+                if let Some((min, max)) = step.weight_clip {
+                    self.weights[i].clamp(min, max);
+                }
+            }
+        } else {
+            self.weights.update(
+                &grad.weights,
+                &mut momentum.weights,
+                &mut velocity.weights,
+                opt,
+                step,
+            );
+
+            if let Some((min, max)) = step.weight_clip {
+                self.weights.clamp(min, max);
+            }
+        }
+
+        self.bias.update(
+            &grad.bias,
+            &mut momentum.bias,
+            &mut velocity.bias,
+            opt,
+            step,
         );
+    }
 
-        self.bias
-            .adam(grad.bias, &mut momentum.bias, &mut velocity.bias, adj, lr);
+    fn layer_shapes(shapes: &mut Vec<LayerShape>) {
+        shapes.push(LayerShape::new::<Self>(
+            &format!("SparseConnected<{}>", T::NAME),
+            &[M, N],
+        ));
+    }
+
+    fn touched_rows(input: &Self::InputType, rows: &mut TouchedRows) {
+        for &feat in input.iter() {
+            rows.insert(feat);
+        }
     }
 
     fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
@@ -101,6 +149,7 @@ impl<T: Activation, const M: usize, const N: usize> FeedForwardNetwork
         }
 
         Self::Layers {
+            pre: res,
             out: res.activate::<T>(),
         }
     }
@@ -112,7 +161,7 @@ impl<T: Activation, const M: usize, const N: usize> FeedForwardNetwork
         mut out_err: Self::OutputType,
         layers: &Self::Layers,
     ) -> Self::InputType {
-        out_err = out_err * layers.out.derivative::<T>();
+        out_err = out_err * layers.pre.derivative::<T>();
 
         for &feat in input.iter() {
             grad.weights[feat] += out_err;
@@ -123,6 +172,8 @@ impl<T: Activation, const M: usize, const N: usize> FeedForwardNetwork
     }
 }
 
+impl<T: Activation, const M: usize, const N: usize> LazyUpdate for SparseConnected<T, M, N> {}
+
 #[cfg(test)]
 mod test {
     use super::SparseConnected;
@@ -148,4 +199,20 @@ mod test {
         let expected = Vector::from_raw([3.1, 2.1, 2.2]);
         assert_eq!(expected, layer.out(&input));
     }
+
+    #[test]
+    fn sparse_connected_gradients() {
+        use goober_core::{activation::Tanh, gradcheck, loss::Mse, SparseVector, Vector};
+
+        let mut layer: SparseConnected<Tanh, 4, 3> =
+            SparseConnected::from_fn(|i, j| 0.2 * i as f32 - 0.3 * j as f32, |j| 0.1 * j as f32);
+
+        let mut input = SparseVector::with_capacity(2);
+        input.push(1);
+        input.push(3);
+        let target = Vector::from_raw([0.5, -0.5, 0.0]);
+
+        let worst = gradcheck(&mut layer, &input, &target, &Mse, 0.001).unwrap();
+        assert!(worst.error < 1e-2, "{worst}");
+    }
 }
@@ -1,3 +1,7 @@
-pub use goober_core::{activation, FeedForwardNetwork, Matrix, OutputLayer, SparseVector, Vector};
-pub use goober_derive::FeedForwardNetwork;
+pub use goober_core::{
+    activation, format, gradcheck, loss, optimizer, quantise, scheduler, FeedForwardNetwork,
+    GradCheck, LayerShape, LazyUpdate, Loss, LrScheduler, Matrix, Optimizer, OptimizerState,
+    OutputLayer, Quantise, QuantisedNetwork, SparseVector, Step, TouchedRows, Trainer, Vector,
+};
+pub use goober_derive::{FeedForwardNetwork, Quantise};
 pub use goober_layer as layer;
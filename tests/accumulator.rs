@@ -0,0 +1,71 @@
+use goober::{
+    activation::{Identity, ReLU, SCReLU},
+    layer::{Accumulator, DenseConnected, Perspective, SparseConnected},
+    FeedForwardNetwork, SparseVector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<SCReLU, 768, 16>,
+    l2: DenseConnected<ReLU, 16, 4>,
+    l3: DenseConnected<Identity, 4, 1>,
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct DualNet {
+    ft: Perspective<SparseConnected<SCReLU, 768, 8>, 16>,
+    out: DenseConnected<Identity, 16, 1>,
+}
+
+fn features(feats: &[usize]) -> SparseVector {
+    let mut input = SparseVector::with_capacity(feats.len());
+    for &feat in feats {
+        input.push(feat);
+    }
+    input
+}
+
+fn sparse<const N: usize>() -> SparseConnected<SCReLU, 768, N> {
+    SparseConnected::from_fn(
+        |i, j| 0.01 * ((i * 7 + j * 3) % 13) as f32 - 0.05,
+        |j| 0.02 * j as f32,
+    )
+}
+
+#[test]
+fn incremental_updates() {
+    let mut net = TestNet::boxed_and_zeroed();
+    net.l1 = sparse();
+    net.l2 = DenseConnected::from_fn(|i, j| 0.1 - 0.01 * (i + j) as f32, |_| 0.05);
+    net.l3 = DenseConnected::from_fn(|i, _| 0.3 * i as f32, |_| 0.0);
+
+    let mut acc = Accumulator::from_features(&net.l1, &features(&[1, 20, 300]));
+    acc.remove_feature(&net.l1, 20);
+    acc.add_feature(&net.l1, 555);
+
+    let input = features(&[1, 300, 555]);
+    let fresh = Accumulator::from_features(&net.l1, &input);
+    for i in 0..16 {
+        assert!((acc.values()[i] - fresh.values()[i]).abs() < 1e-6);
+    }
+
+    acc.refresh(&net.l1, &input);
+    assert_eq!(acc, fresh);
+
+    let first = net.l1.out_from_accumulator(&acc);
+    assert_eq!(net.out_from_first_layer(&first), net.out(&input));
+}
+
+#[test]
+fn perspective_accumulators() {
+    let mut net = DualNet::boxed_and_zeroed();
+    net.ft = Perspective::from_raw(sparse());
+    net.out = DenseConnected::from_fn(|i, _| 0.2 - 0.02 * i as f32, |_| 0.1);
+
+    let (us, them) = (features(&[4, 60]), features(&[9, 700, 701]));
+    let us_acc = Accumulator::from_features(net.ft.inner(), &us);
+    let them_acc = Accumulator::from_features(net.ft.inner(), &them);
+
+    let first = net.ft.out_from_accumulators(&us_acc, &them_acc);
+    assert_eq!(net.out_from_first_layer(&first), net.out(&(us, them)));
+}
@@ -0,0 +1,104 @@
+use goober::{
+    activation::{Identity, ReLU, SCReLU},
+    layer::{DenseConnected, SparseConnected},
+    FeedForwardNetwork, OutputLayer, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct SparseNet {
+    l1: SparseConnected<SCReLU, 768, 32>,
+    l2: DenseConnected<ReLU, 32, 70>,
+    l3: DenseConnected<Identity, 70, 3>,
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct DenseNet {
+    l1: DenseConnected<ReLU, 100, 80>,
+    l2: DenseConnected<Identity, 80, 2>,
+}
+
+fn rand(seed: &mut u32) -> f32 {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 17;
+    *seed ^= *seed << 5;
+    *seed as f32 / u32::MAX as f32 - 0.5
+}
+
+fn bytes<N: FeedForwardNetwork>(net: &N) -> Vec<u8> {
+    let mut buf = Vec::new();
+    net.save(&mut buf).unwrap();
+    buf
+}
+
+/// Checks that batched passes give exactly the same outputs,
+/// gradients and input errors as running each sample in turn.
+fn check<N>(net: &N, inputs: &[N::InputType], errs: &[N::OutputType]) -> Vec<N::InputType>
+where
+    N: FeedForwardNetwork,
+    N::OutputType: PartialEq + std::fmt::Debug,
+{
+    let layers = net.out_with_layers_batch(inputs);
+    let mut grad = N::boxed_and_zeroed();
+    let input_errs = net.backprop_batch(inputs, &mut grad, errs.to_vec(), &layers);
+
+    let mut expected_grad = N::boxed_and_zeroed();
+    for (i, input) in inputs.iter().enumerate() {
+        let expected = net.out_with_layers(input);
+        assert_eq!(layers[i].output_layer(), expected.output_layer());
+        net.backprop(input, &mut expected_grad, errs[i].clone(), &expected);
+    }
+
+    assert_eq!(net.out_batch(inputs).len(), inputs.len());
+    assert!(bytes(grad.as_ref()) == bytes(expected_grad.as_ref()));
+    input_errs
+}
+
+#[test]
+fn sparse_batch() {
+    let mut seed = 0x2468_ace0;
+    let mut net = SparseNet::boxed_and_zeroed();
+    net.l1 = SparseConnected::from_fn(|_, _| rand(&mut seed), |_| 0.1);
+    net.l2 = DenseConnected::from_fn(|_, _| rand(&mut seed), |_| 0.05);
+    net.l3 = DenseConnected::from_fn(|_, _| rand(&mut seed), |_| 0.0);
+
+    let inputs = (0..37)
+        .map(|b| {
+            let mut input = SparseVector::with_capacity(16);
+            for k in 0..16 {
+                input.push((b * 131 + k * 47) % 768);
+            }
+            input
+        })
+        .collect::<Vec<_>>();
+    let errs = (0..37)
+        .map(|_| Vector::from_fn(|_| rand(&mut seed)))
+        .collect::<Vec<_>>();
+
+    check(net.as_ref(), &inputs, &errs);
+}
+
+#[test]
+fn dense_batch() {
+    let mut seed = 0x1357_9bdf;
+    let mut net = DenseNet::boxed_and_zeroed();
+    net.l1 = DenseConnected::from_fn(|_, _| rand(&mut seed), |_| 0.1);
+    net.l2 = DenseConnected::from_fn(|_, _| rand(&mut seed), |_| 0.0);
+
+    let inputs = (0..41)
+        .map(|_| Vector::from_fn(|_| rand(&mut seed)))
+        .collect::<Vec<_>>();
+    let errs = (0..41)
+        .map(|_| Vector::from_fn(|_| rand(&mut seed)))
+        .collect::<Vec<_>>();
+
+    let input_errs = check(net.as_ref(), &inputs, &errs);
+
+    let mut grad = DenseNet::boxed_and_zeroed();
+    for (i, input) in inputs.iter().enumerate() {
+        let layers = net.out_with_layers(input);
+        assert_eq!(
+            input_errs[i],
+            net.backprop(input, &mut grad, errs[i], &layers)
+        );
+    }
+}
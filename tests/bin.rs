@@ -0,0 +1,108 @@
+use goober::{
+    activation::{CReLU, ReLU},
+    layer::{DenseConnected, SparseConnected},
+    FeedForwardNetwork, SparseVector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 768, 8>,
+    l2: DenseConnected<ReLU, 8, 1>,
+}
+
+#[test]
+fn write_then_read() {
+    let mut net = TestNet::boxed_and_zeroed();
+    *net.l1.bias_mut() = goober::Vector::from_fn(|i| i as f32 * 0.25);
+    *net.l2.weights_col_mut(3) = goober::Vector::from_raw([1.5]);
+
+    let path = std::env::temp_dir().join("goober_write_then_read.bin");
+    let path = path.to_str().unwrap();
+    net.write_to_bin(path).unwrap();
+
+    let loaded = TestNet::read_from_bin(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let mut input = SparseVector::with_capacity(8);
+    input.push(7);
+    assert_eq!(net.out(&input), loaded.out(&input));
+    assert_eq!(net.l1.bias(), loaded.l1.bias());
+}
+
+#[test]
+fn load_wrong_size() {
+    let bytes = [0u8; 12];
+    assert!(TestNet::load_from_bytes(&bytes).is_err());
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct OtherNet {
+    l1: SparseConnected<ReLU, 768, 4>,
+    l2: DenseConnected<ReLU, 4, 3>,
+}
+
+#[test]
+fn reject_other_network() {
+    use goober::format::FormatError;
+
+    let path = std::env::temp_dir().join("goober_reject_other_network.bin");
+    let path = path.to_str().unwrap();
+    OtherNet::boxed_and_zeroed().write_to_bin(path).unwrap();
+
+    let bytes = std::fs::read(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let err = TestNet::load_from_bytes(&bytes).err().unwrap();
+    let err = err.get_ref().unwrap().downcast_ref::<FormatError>();
+    assert!(matches!(err, Some(FormatError::ShapeMismatch { .. })));
+
+    let mut corrupted = bytes.clone();
+    let mid = corrupted.len() / 2;
+    corrupted[mid] ^= 1;
+    let err = OtherNet::load_from_bytes(&corrupted).err().unwrap();
+    let err = err.get_ref().unwrap().downcast_ref::<FormatError>();
+    assert_eq!(err, Some(&FormatError::ChecksumMismatch));
+
+    assert!(OtherNet::load_from_bytes(&bytes).is_ok());
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct ClippedNet {
+    l1: SparseConnected<CReLU, 768, 8>,
+    l2: DenseConnected<ReLU, 8, 1>,
+}
+
+#[test]
+fn reject_other_activation() {
+    use goober::format::FormatError;
+
+    let mut bytes = Vec::new();
+    ClippedNet::boxed_and_zeroed().save(&mut bytes).unwrap();
+
+    let err = TestNet::load(bytes.as_slice()).err().unwrap();
+    let err = err.get_ref().unwrap().downcast_ref::<FormatError>();
+    assert!(matches!(err, Some(FormatError::ShapeMismatch { .. })));
+
+    assert_eq!(
+        err.unwrap().to_string(),
+        "network shape mismatch: expected [SparseConnected<ReLU>[768, 8], \
+         DenseConnected<ReLU>[8, 1]], found [SparseConnected<CReLU>[768, 8], \
+         DenseConnected<ReLU>[8, 1]]"
+    );
+}
+
+#[test]
+fn save_to_buffer() {
+    let mut net = TestNet::boxed_and_zeroed();
+    *net.l2.bias_mut() = goober::Vector::from_raw([-0.5]);
+
+    let mut buf = Vec::new();
+    net.save(&mut buf).unwrap();
+
+    let loaded = TestNet::load(buf.as_slice()).unwrap();
+    assert_eq!(net.l2.bias(), loaded.l2.bias());
+
+    assert!(TestNet::load(&buf[..buf.len() - 1]).is_err());
+    assert!(TestNet::read_from_bin("/nonexistent/dir/net.bin").is_err());
+    assert!(net.write_to_bin("/nonexistent/dir/net.bin").is_err());
+}
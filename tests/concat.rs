@@ -0,0 +1,42 @@
+use goober::{
+    activation::{Identity, ReLU},
+    gradcheck,
+    layer::{Concat, DenseConnected, SparseConnected},
+    loss::Mse,
+    FeedForwardNetwork, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: Concat<SparseConnected<ReLU, 768, 8>, SparseConnected<ReLU, 768, 4>, 12>,
+    l2: DenseConnected<Identity, 12, 1>,
+}
+
+#[test]
+fn concat() {
+    let mut net = TestNet::boxed_and_zeroed();
+    let a = SparseConnected::from_fn(
+        |i, j| 0.01 * (i % 7) as f32 - 0.02 * j as f32,
+        |j| 0.1 * j as f32,
+    );
+    let b = SparseConnected::from_fn(|i, j| 0.03 * (i % 5) as f32 + 0.01 * j as f32, |_| -0.05);
+    net.l1 = Concat::from_raw(a, b);
+    net.l2 = DenseConnected::from_fn(|i, _| 0.2 - 0.03 * i as f32, |_| 0.1);
+
+    let mut input = SparseVector::with_capacity(8);
+    input.push(5);
+    input.push(300);
+
+    let out = net.l1.out(&input);
+    let (a_out, b_out) = (a.out(&input), b.out(&input));
+    for i in 0..8 {
+        assert_eq!(out[i], a_out[i]);
+    }
+    for i in 0..4 {
+        assert_eq!(out[8 + i], b_out[i]);
+    }
+
+    let target = Vector::from_raw([1.0]);
+    let worst = gradcheck(net.as_mut(), &input, &target, &Mse, 0.001).unwrap();
+    assert!(worst.error < 1e-2, "{worst}");
+}
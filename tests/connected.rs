@@ -1,6 +1,7 @@
 use goober::{
     activation::ReLU,
     layer::{DenseConnected, SparseConnected},
+    optimizer::{Adam, Step},
     FeedForwardNetwork, SparseVector,
 };
 
@@ -24,3 +25,23 @@ fn connected() {
     input.push(5);
     let _ = net.out(&input);
 }
+
+#[test]
+fn connected_update() {
+    let mut net = TestNet::boxed_and_zeroed();
+    let mut grad = TestNet::boxed_and_zeroed();
+    let mut m = TestNet::boxed_and_zeroed();
+    let mut v = TestNet::boxed_and_zeroed();
+
+    *grad.l2.l2.bias_mut() = goober::Vector::from_raw([1.0]);
+    net.update(
+        &grad,
+        &mut m,
+        &mut v,
+        &Adam::default(),
+        &Step::new(1.0, 0.1, 1),
+    );
+
+    assert!(net.l2.l2.bias()[0] < 0.0);
+    assert_eq!(net.l2.l1.bias(), goober::Vector::zeroed());
+}
@@ -0,0 +1,140 @@
+use goober::{
+    activation::{Identity, ReLU, SCReLU, Tanh},
+    gradcheck,
+    layer::{
+        same_padding, Add, AvgPool2D, Conv1D, Conv2D, DenseConnected, Flatten, MaxPool2D, Softmax,
+        SparseConnected,
+    },
+    loss::{CrossEntropy, Mse},
+    FeedForwardNetwork, Loss, Matrix, OutputLayer, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: Add<SparseConnected<SCReLU, 16, 1>, SubTestNet>,
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct SubTestNet {
+    l1: SparseConnected<ReLU, 16, 8>,
+    l2: DenseConnected<Tanh, 8, 1>,
+}
+
+fn weight(i: usize, j: usize) -> f32 {
+    ((i * 7 + j * 3) as f32 * 0.91).sin() * 0.5
+}
+
+#[test]
+fn derived_gradients() {
+    let mut net = TestNet::boxed_and_zeroed();
+    net.l1 = Add::from_raw(
+        SparseConnected::from_fn(weight, |_| 0.3),
+        SubTestNet {
+            l1: SparseConnected::from_fn(weight, |j| 0.05 * j as f32),
+            l2: DenseConnected::from_fn(weight, |_| -0.1),
+        },
+    );
+
+    let mut input = SparseVector::with_capacity(4);
+    for feat in [1, 4, 9, 13] {
+        input.push(feat);
+    }
+    let target = Vector::from_raw([0.7]);
+
+    let worst = gradcheck(net.as_mut(), &input, &target, &Mse, 0.001).unwrap();
+    assert!(worst.error < 1e-2, "{worst}");
+}
+
+#[test]
+fn layer_names() {
+    let shapes = goober::format::shapes_of::<TestNet>();
+
+    let names = shapes.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["l1.a", "l1.b.l1", "l1.b.l2"]);
+
+    let total = shapes.iter().map(|s| s.len).sum::<usize>();
+    assert_eq!(total * 4, std::mem::size_of::<TestNet>());
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct ConvNet {
+    l1: DenseConnected<Tanh, 4, 16>,
+    l2: Conv1D<Tanh, 16, 8, 3, 2, 2, 2, 1, { same_padding(3, 1) }>,
+    l3: DenseConnected<Tanh, 8, 1>,
+}
+
+#[test]
+fn conv1d_input_gradients() {
+    let mut net = ConvNet::boxed_and_zeroed();
+    net.l1 = DenseConnected::from_fn(weight, |j| 0.1 * j as f32);
+    net.l2 = Conv1D::from_fn(|o, c, k| weight(o * 2 + c, k), |_| 0.05);
+    net.l3 = DenseConnected::from_fn(weight, |_| 0.0);
+
+    let input = Vector::from_raw([0.3, -0.6, 0.9, 0.1]);
+    let target = Vector::from_raw([0.4]);
+
+    let worst = gradcheck(net.as_mut(), &input, &target, &Mse, 0.001).unwrap();
+    assert!(worst.error < 1e-2, "{worst}");
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct BoardNet {
+    l1: Conv2D<Tanh, 2, 3, 4, 4, 3>,
+    l2: MaxPool2D<3, 4, 4, 2, 2>,
+    l3: Conv2D<Tanh, 3, 2, 2, 2, 1>,
+    l4: AvgPool2D<2, 2, 2, 1, 2>,
+    l5: Flatten<2, 1, 2, 4>,
+    l6: DenseConnected<Tanh, 4, 1>,
+}
+
+#[test]
+fn conv2d_gradients() {
+    let mut net = BoardNet::boxed_and_zeroed();
+    net.l1 = Conv2D::from_fn(
+        |o, c, i, j| weight(o * 2 + c, i * 3 + j),
+        |o| 0.1 * o as f32,
+    );
+    net.l3 = Conv2D::from_fn(|o, c, _, _| weight(o, c), |_| -0.05);
+    net.l6 = DenseConnected::from_fn(weight, |_| 0.0);
+
+    let input = [
+        Matrix::from_fn(|i, j| weight(i, j + 1)),
+        Matrix::from_fn(|i, j| weight(i + 2, j)),
+    ];
+    let target = Vector::from_raw([0.4]);
+
+    let worst = gradcheck(net.as_mut(), &input, &target, &Mse, 0.001).unwrap();
+    assert!(worst.error < 1e-2, "{worst}");
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct PolicyNet {
+    l1: DenseConnected<ReLU, 4, 8>,
+    l2: DenseConnected<Identity, 8, 5>,
+    l3: Softmax<5>,
+}
+
+#[test]
+fn softmax_cross_entropy() {
+    let mut net = PolicyNet::boxed_and_zeroed();
+    net.l1 = DenseConnected::from_fn(weight, |j| 0.1 * j as f32);
+    net.l2 = DenseConnected::from_fn(weight, |_| 0.0);
+
+    let input = Vector::from_raw([0.3, -0.6, 0.9, 0.1]);
+    let target = Vector::from_raw([0.0, 0.5, 0.25, 0.0, 0.25]);
+
+    let worst = gradcheck(net.as_mut(), &input, &target, &CrossEntropy, 0.001).unwrap();
+    assert!(worst.error < 1e-2, "{worst}");
+
+    // with a softmax output, the error reaching the logits is `probs - target`
+    let layers = net.out_with_layers(&input);
+    let probs = layers.output_layer();
+    let (_, err) = CrossEntropy.loss(&probs, &target);
+    let logits_err = net
+        .l3
+        .backprop(&Vector::zeroed(), &mut Softmax::new(), err, &layers.l3);
+
+    for i in 0..5 {
+        assert!((logits_err[i] - (probs[i] - target[i])).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,276 @@
+use goober::{
+    activation::{Identity, ReLU},
+    layer::{Concat, DenseConnected, PReLU, Perspective, SparseConnected},
+    loss::Mse,
+    optimizer::{Adam, AdamW, Sgd, Step},
+    scheduler::Constant,
+    FeedForwardNetwork, OptimizerState, SparseVector, TouchedRows, Trainer, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    #[optimizer(lr_scale = 0.1, weight_decay = 0.0)]
+    l1: SparseConnected<ReLU, 768, 4>,
+    #[optimizer(weight_decay = 0.5)]
+    l2: DenseConnected<ReLU, 4, 1>,
+}
+
+#[test]
+fn parameter_groups() {
+    let mut net = TestNet::boxed_and_zeroed();
+    let mut grad = TestNet::boxed_and_zeroed();
+    let mut m = TestNet::boxed_and_zeroed();
+    let mut v = TestNet::boxed_and_zeroed();
+
+    *net.l1.bias_mut() = Vector::from_raw([1.0; 4]);
+    *net.l2.bias_mut() = Vector::from_raw([1.0]);
+    *grad.l1.bias_mut() = Vector::from_raw([1.0; 4]);
+
+    let step = Step::new(1.0, 0.1, 1);
+    net.update(&grad, &mut m, &mut v, &Sgd::default(), &step);
+
+    assert!((net.l1.bias()[0] - 0.99).abs() < 1e-6);
+    assert!((net.l2.bias()[0] - 0.95).abs() < 1e-6);
+
+    let mut net = TestNet::boxed_and_zeroed();
+    *net.l1.bias_mut() = Vector::from_raw([1.0; 4]);
+    let grad = TestNet::boxed_and_zeroed();
+    let mut m = TestNet::boxed_and_zeroed();
+    let mut v = TestNet::boxed_and_zeroed();
+    net.update(&grad, &mut m, &mut v, &AdamW::default(), &step);
+    assert_eq!(net.l1.bias()[0], 1.0);
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct ClippedNet {
+    #[optimizer(clip = (-0.5, 0.25))]
+    l1: SparseConnected<ReLU, 8, 4>,
+    #[optimizer(clip = 1.98)]
+    l2: DenseConnected<ReLU, 4, 1>,
+}
+
+#[test]
+fn weight_clipping() {
+    let mut net = ClippedNet::boxed_and_zeroed();
+    let mut grad = ClippedNet::boxed_and_zeroed();
+    let mut m = ClippedNet::boxed_and_zeroed();
+    let mut v = ClippedNet::boxed_and_zeroed();
+
+    *grad.l1.weights_row_mut(2) = Vector::from_raw([-10.0, 10.0, 0.0, 0.0]);
+    *grad.l1.bias_mut() = Vector::from_raw([-10.0; 4]);
+    *grad.l2.weights_col_mut(1) = Vector::from_raw([-10.0]);
+
+    net.update(
+        &grad,
+        &mut m,
+        &mut v,
+        &Sgd::default(),
+        &Step::new(1.0, 1.0, 1),
+    );
+
+    assert_eq!(
+        net.l1.weights_row(2),
+        Vector::from_raw([0.25, -0.5, 0.0, 0.0])
+    );
+    assert_eq!(net.l1.bias(), Vector::from_raw([10.0; 4]));
+    assert_eq!(*net.l2.weights_col(1), Vector::from_raw([1.98]));
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct PReLUNet {
+    l1: DenseConnected<Identity, 2, 2>,
+    l2: PReLU<2>,
+}
+
+#[test]
+fn learnable_activation() {
+    let mut net = PReLUNet::boxed_and_zeroed();
+    net.l1 = DenseConnected::from_fn(|i, j| if i == j { 1.0 } else { 0.0 }, |_| 0.0);
+    net.l2 = PReLU::from_raw(Vector::from_raw([0.25, 0.25]));
+
+    let input = Vector::from_raw([-1.0, 1.0]);
+    let layers = net.out_with_layers(&input);
+
+    let mut grad = PReLUNet::boxed_and_zeroed();
+    net.backprop(&input, &mut grad, Vector::from_raw([1.0, 1.0]), &layers);
+    assert_eq!(grad.l2.slopes(), Vector::from_raw([-1.0, 0.0]));
+
+    let mut m = PReLUNet::boxed_and_zeroed();
+    let mut v = PReLUNet::boxed_and_zeroed();
+    net.update(
+        &grad,
+        &mut m,
+        &mut v,
+        &Sgd::default(),
+        &Step::new(1.0, 0.1, 1),
+    );
+    assert_eq!(net.l2.slopes(), Vector::from_raw([0.35, 0.25]));
+
+    let mut bytes = Vec::new();
+    net.save(&mut bytes).unwrap();
+    let loaded = PReLUNet::load(bytes.as_slice()).unwrap();
+    assert_eq!(loaded.l2.slopes(), net.l2.slopes());
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct LazyNet {
+    #[optimizer(lazy)]
+    l1: SparseConnected<ReLU, 64, 4>,
+    l2: DenseConnected<Identity, 4, 1>,
+}
+
+// layers wrapping sparse layers can be updated lazily too
+#[derive(FeedForwardNetwork)]
+pub struct LazyPerspectiveNet {
+    #[optimizer(lazy)]
+    ft: Perspective<SparseConnected<ReLU, 64, 4>, 8>,
+    out: DenseConnected<Identity, 8, 1>,
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct LazyConcatNet {
+    #[optimizer(lazy)]
+    ft: Concat<SparseConnected<ReLU, 64, 2>, SparseConnected<ReLU, 64, 2>, 4>,
+    out: DenseConnected<Identity, 4, 1>,
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct EagerNet {
+    l1: SparseConnected<ReLU, 64, 4>,
+    l2: DenseConnected<Identity, 4, 1>,
+}
+
+// only networks with lazy groups need the rows touched by each batch
+const _: () = assert!(LazyNet::LAZY && LazyPerspectiveNet::LAZY && LazyConcatNet::LAZY);
+const _: () = assert!(!EagerNet::LAZY);
+
+fn input(feats: [usize; 2]) -> SparseVector {
+    let mut input = SparseVector::with_capacity(2);
+    for feat in feats {
+        input.push(feat);
+    }
+    input
+}
+
+fn lazy_step(net: &mut LazyNet, state: &mut OptimizerState<LazyNet>, feats: [usize; 2]) {
+    let input = input(feats);
+    let mut touched = TouchedRows::default();
+    LazyNet::touched_rows(&input, &mut touched);
+
+    let mut grad = LazyNet::boxed_and_zeroed();
+    let layers = net.out_with_layers(&input);
+    net.backprop(&input, &mut grad, Vector::from_raw([1.0]), &layers);
+    state.step(net, &grad, &Adam::default(), 1.0, 0.01, Some(&touched));
+}
+
+#[test]
+fn lazy_sparse_updates() {
+    let mut lazy = LazyNet::boxed_and_zeroed();
+    let mut eager = EagerNet::boxed_and_zeroed();
+    let mut lazy_state = OptimizerState::new();
+    let mut eager_state = OptimizerState::<EagerNet>::new();
+
+    lazy.l1 = SparseConnected::from_fn(|i, j| 0.01 * (i + j) as f32, |_| 0.1);
+    lazy.l2 = DenseConnected::from_fn(|i, _| 0.5 - 0.2 * i as f32, |_| 0.0);
+    eager.l1 = lazy.l1;
+    eager.l2 = lazy.l2;
+
+    for (t, feats) in [[1, 5], [2, 9], [2, 9]].into_iter().enumerate() {
+        if t == 2 {
+            // every output is inactive, so the active rows have a zero gradient
+            *lazy.l1.bias_mut() = Vector::from_raw([-10.0; 4]);
+            *eager.l1.bias_mut() = Vector::from_raw([-10.0; 4]);
+        }
+
+        lazy_step(&mut lazy, &mut lazy_state, feats);
+
+        let input = input(feats);
+        let mut grad = EagerNet::boxed_and_zeroed();
+        let layers = eager.out_with_layers(&input);
+        eager.backprop(&input, &mut grad, Vector::from_raw([1.0]), &layers);
+        eager_state.step(&mut eager, &grad, &Adam::default(), 1.0, 0.01, None);
+
+        // the first batch is the first step of every row, lazy or not
+        if t == 0 {
+            for feat in feats {
+                assert_eq!(lazy.l1.weights_row(feat), eager.l1.weights_row(feat));
+                assert_eq!(
+                    lazy_state.momentum().l1.weights_row(feat),
+                    eager_state.momentum().l1.weights_row(feat)
+                );
+            }
+        }
+        assert_eq!(lazy.l1.bias(), eager.l1.bias());
+    }
+
+    assert_eq!(lazy_state.row_steps(), [0, 1, 2, 0, 0, 1, 0, 0, 0, 2]);
+
+    // rows active only in the first batch keep moving with momentum unless lazy
+    let initial = SparseConnected::<ReLU, 64, 4>::from_fn(|i, j| 0.01 * (i + j) as f32, |_| 0.1);
+    assert_ne!(lazy.l1.weights_row(1), initial.weights_row(1));
+    assert_ne!(lazy.l1.weights_row(1), eager.l1.weights_row(1));
+    assert_eq!(
+        eager_state.momentum().l1.weights_row(1),
+        0.9 * (0.9 * lazy_state.momentum().l1.weights_row(1))
+    );
+    assert_eq!(lazy.l1.weights_row(30), initial.weights_row(30));
+}
+
+#[test]
+fn lazy_row_bias_correction() {
+    let mut net = LazyNet::boxed_and_zeroed();
+    net.l1 = SparseConnected::from_fn(|i, j| 0.01 * (i + j) as f32, |_| 0.1);
+    net.l2 = DenseConnected::from_fn(|i, _| 0.5 - 0.2 * i as f32, |_| 0.0);
+    let mut state = OptimizerState::new();
+
+    lazy_step(&mut net, &mut state, [1, 5]);
+    lazy_step(&mut net, &mut state, [1, 5]);
+
+    let mut fresh = LazyNet::boxed_and_zeroed();
+    fresh.l1 = net.l1;
+    fresh.l2 = net.l2;
+    let mut fresh_state = OptimizerState::new();
+
+    // rows first touched at the third step are updated as by a fresh optimiser
+    lazy_step(&mut net, &mut state, [2, 9]);
+    lazy_step(&mut fresh, &mut fresh_state, [2, 9]);
+    assert_eq!(state.steps(), 3);
+
+    let mut bytes = Vec::new();
+    state.save(&mut bytes).unwrap();
+    let loaded = OptimizerState::<LazyNet>::load(&mut bytes.as_slice()).unwrap();
+    assert_eq!(loaded.row_steps(), [0, 2, 1, 0, 0, 2, 0, 0, 0, 1]);
+
+    for feat in [2, 9] {
+        assert_eq!(net.l1.weights_row(feat), fresh.l1.weights_row(feat));
+        assert_eq!(
+            state.velocity().l1.weights_row(feat),
+            fresh_state.velocity().l1.weights_row(feat)
+        );
+    }
+}
+
+#[test]
+fn lazy_trainer_updates_touched_rows() {
+    let mut net = LazyNet::boxed_and_zeroed();
+    net.l1 = SparseConnected::from_fn(|i, j| 0.01 * (i + j) as f32, |_| 0.1);
+    net.l2 = DenseConnected::from_fn(|i, _| 0.5 - 0.2 * i as f32, |_| 0.0);
+    let initial = net.l1;
+
+    let data = [[3, 40], [7, 40]].map(|feats| {
+        let mut input = SparseVector::with_capacity(2);
+        for feat in feats {
+            input.push(feat);
+        }
+        (input, Vector::from_raw([1.0]))
+    });
+
+    let mut trainer = Trainer::new(net, Adam::default(), Mse, Constant { lr: 0.01 }, 2);
+    trainer.train_batch(&data);
+
+    let net = trainer.network();
+    for feat in [3, 7, 40] {
+        assert_ne!(net.l1.weights_row(feat), initial.weights_row(feat));
+    }
+    assert_eq!(net.l1.weights_row(4), initial.weights_row(4));
+}
@@ -0,0 +1,49 @@
+use goober::{
+    activation::{Identity, SCReLU},
+    gradcheck,
+    layer::{DenseConnected, Perspective, SparseConnected},
+    loss::Mse,
+    FeedForwardNetwork, OutputLayer, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct ChessNet {
+    ft: Perspective<SparseConnected<SCReLU, 768, 8>, 16>,
+    out: DenseConnected<Identity, 16, 1>,
+}
+
+fn features(feats: &[usize]) -> SparseVector {
+    let mut input = SparseVector::with_capacity(feats.len());
+    for &feat in feats {
+        input.push(feat);
+    }
+    input
+}
+
+#[test]
+fn perspective() {
+    let mut net = ChessNet::boxed_and_zeroed();
+    let ft = SparseConnected::from_fn(
+        |i, j| 0.05 * (i % 11) as f32 - 0.02 * j as f32,
+        |j| 0.1 + 0.05 * j as f32,
+    );
+    net.ft = Perspective::from_raw(ft);
+    net.out = DenseConnected::from_fn(|i, _| 0.3 - 0.04 * i as f32, |_| 0.0);
+
+    let us = features(&[3, 100, 250]);
+    let them = features(&[7, 421]);
+
+    // outputs are concatenated in input order
+    let layers = net.out_with_layers(&(us.clone(), them.clone()));
+    let (us_out, them_out) = (ft.out(&us), ft.out(&them));
+    let out = layers.ft.output_layer();
+    for i in 0..8 {
+        assert_eq!(out[i], us_out[i]);
+        assert_eq!(out[8 + i], them_out[i]);
+    }
+
+    // both halves train the shared weights
+    let target = Vector::from_raw([1.0]);
+    let worst = gradcheck(net.as_mut(), &(us, them), &target, &Mse, 0.001).unwrap();
+    assert!(worst.error < 1e-2, "{worst}");
+}
@@ -0,0 +1,270 @@
+use goober::{
+    activation::{CReLU, Identity, ReLU, SCReLU},
+    layer::{DenseConnected, Perspective, SparseConnected},
+    loss::Mse,
+    optimizer::Adam,
+    quantise::{quantisation_error, QuantiseError, Quantiser, Rounding, Scales},
+    scheduler::Constant,
+    FeedForwardNetwork, OutputLayer, Quantise, QuantisedNetwork, SparseVector, Trainer, Vector,
+};
+
+#[derive(FeedForwardNetwork, Quantise)]
+pub struct ChessNet {
+    #[quantise(weights = 255, output = 255)]
+    ft: Perspective<SparseConnected<SCReLU, 768, 16>, 32>,
+    #[quantise(weights = 64, output = 255)]
+    l1: DenseConnected<CReLU, 32, 8>,
+    #[quantise(weights = 64)]
+    out: DenseConnected<Identity, 8, 1>,
+}
+
+#[derive(FeedForwardNetwork, Quantise)]
+pub struct Unscaled {
+    #[quantise(weights = 64)]
+    l1: DenseConnected<ReLU, 4, 4>,
+    l2: DenseConnected<Identity, 4, 1>,
+}
+
+#[derive(FeedForwardNetwork, Quantise)]
+pub struct SmallNet {
+    #[quantise(weights = 8, output = 16)]
+    l1: DenseConnected<ReLU, 2, 8>,
+    #[quantise(weights = 8)]
+    l2: DenseConnected<Identity, 8, 1>,
+}
+
+fn net() -> Box<ChessNet> {
+    let mut net = ChessNet::boxed_and_zeroed();
+    net.ft = Perspective::from_raw(SparseConnected::from_fn(
+        |i, j| 0.03 * ((i * 5 + j * 11) % 17) as f32 - 0.2,
+        |j| 0.05 * (j % 4) as f32,
+    ));
+    net.l1 = DenseConnected::from_fn(
+        |i, j| 0.1 * ((i + 3 * j) % 7) as f32 - 0.3,
+        |j| 0.02 * j as f32,
+    );
+    net.out = DenseConnected::from_fn(|i, _| 0.4 - 0.1 * i as f32, |_| 0.1);
+    net
+}
+
+fn positions() -> Vec<(SparseVector, SparseVector)> {
+    (0..32)
+        .map(|p| {
+            let mut us = SparseVector::with_capacity(8);
+            let mut them = SparseVector::with_capacity(8);
+            for k in 0..8 {
+                us.push((p * 37 + k * 101) % 768);
+                them.push((p * 53 + k * 89 + 7) % 768);
+            }
+            (us, them)
+        })
+        .collect()
+}
+
+#[test]
+fn integer_inference() {
+    let net = net();
+    let q = Quantiser::default();
+    let quantised = net.quantise(&q, 1, Default::default()).unwrap();
+
+    assert_eq!(quantised.output_scale(), 255 * 64);
+
+    let report = quantisation_error(net.as_ref(), &quantised, &q, 1, &positions());
+    assert_eq!(report.outputs, 32);
+    assert!(report.max_error < 0.02, "{report}");
+    assert!(report.mean_error <= report.max_error);
+
+    let q = Quantiser {
+        rounding: Rounding::Truncate,
+        ..Default::default()
+    };
+    let truncated = net.quantise(&q, 1, Default::default()).unwrap();
+    let report = quantisation_error(net.as_ref(), &truncated, &q, 1, &positions());
+    assert!(report.max_error < 0.1, "{report}");
+}
+
+#[test]
+fn saturation() {
+    let mut net = net();
+    *net.l1.weights_col_mut(3) = goober::Vector::from_fn(|j| if j == 5 { 2.5 } else { 0.0 });
+
+    let err = net
+        .quantise(&Quantiser::default(), 1, Default::default())
+        .err();
+    assert!(matches!(
+        err,
+        Some(QuantiseError::Saturated { ref layer, value, scale: 64 }) if layer == "l1" && value == 2.5
+    ));
+
+    let q = Quantiser {
+        saturate: true,
+        ..Default::default()
+    };
+    assert!(net.quantise(&q, 1, Default::default()).is_ok());
+}
+
+#[test]
+fn missing_scale() {
+    let net = Unscaled::boxed_and_zeroed();
+    let err = net
+        .quantise(&Quantiser::default(), 64, Default::default())
+        .err();
+    assert_eq!(
+        err,
+        Some(QuantiseError::MissingScale {
+            layer: String::from("l2")
+        })
+    );
+}
+
+#[test]
+fn outer_scales() {
+    let net = Unscaled::boxed_and_zeroed();
+    let q = Quantiser::default();
+
+    // fields without their own scales use the ones given to the network
+    let scales = Scales {
+        weights: Some(32),
+        output: None,
+    };
+    let quantised = net.quantise(&q, 64, scales).unwrap();
+    assert_eq!(quantised.output_scale(), 64 * 64 * 32);
+
+    // `output` only applies to the last field
+    let scales = Scales {
+        weights: Some(32),
+        output: Some(100),
+    };
+    let quantised = net.quantise(&q, 64, scales).unwrap();
+    assert_eq!(quantised.output_scale(), 100);
+    assert_eq!(quantised.l1.output_scale(), 64 * 64);
+
+    let input = Vector::from_raw([0.5, -0.25, 1.0, 0.0]);
+    let (_, fake_scale) = net
+        .out_with_layers_fake_quantised(&input, &q, 64, scales)
+        .unwrap();
+    assert_eq!(fake_scale, 100);
+}
+
+#[test]
+fn scale_overflow() {
+    let net = SmallNet::boxed_and_zeroed();
+    let err = net
+        .quantise(&Quantiser::default(), i32::MAX / 4, Default::default())
+        .err();
+    assert_eq!(
+        err,
+        Some(QuantiseError::ScaleOverflow {
+            layer: String::from("l1"),
+            input_scale: i32::MAX / 4,
+            weight_scale: 8,
+        })
+    );
+}
+
+#[test]
+fn large_sums() {
+    let mut net = SmallNet::boxed_and_zeroed();
+    net.l1 = DenseConnected::from_fn(|_, _| 15.0, |_| 0.0);
+    net.l2 = DenseConnected::from_fn(|_, _| 15.0, |_| 0.0);
+
+    // the sums in `l1` are far outside the range of an `i32`
+    let q = Quantiser::default();
+    let input = Vector::from_raw([2000.0, 2000.0]);
+    let quantised = net.quantise(&q, 1 << 20, Default::default()).unwrap();
+    let actual = quantised.out(&SmallNet::quantise_input(&q, &input, 1 << 20))[0];
+    assert_eq!(
+        actual as f32 / quantised.output_scale() as f32,
+        net.out(&input)[0]
+    );
+}
+
+#[test]
+fn fake_quantised_forward() {
+    let net = net();
+    let q = Quantiser::default();
+    let quantised = net.quantise(&q, 1, Default::default()).unwrap();
+    let scale = quantised.output_scale() as f32;
+
+    for position in positions() {
+        let (layers, fake_scale) = net
+            .out_with_layers_fake_quantised(&position, &q, 1, Default::default())
+            .unwrap();
+        assert_eq!(fake_scale, quantised.output_scale());
+
+        let fake = layers.output_layer()[0];
+        let actual = quantised.out(&position)[0] as f32 / scale;
+        assert!((fake - actual).abs() < 1e-4, "{fake} != {actual}");
+    }
+}
+
+#[test]
+fn quantisation_aware_training() {
+    let data = (0..256)
+        .map(|i| {
+            // not multiples of 1/16, so the input is rounded before the first layer
+            let (x, y) = (
+                (i % 16) as f32 * 0.0613 - 0.47,
+                (i / 16) as f32 * 0.0587 - 0.43,
+            );
+            (Vector::from_raw([x, y]), Vector::from_raw([x * 2.0 - y]))
+        })
+        .collect::<Vec<_>>();
+
+    let mut net = SmallNet::boxed_and_zeroed();
+    net.l1 = DenseConnected::from_fn(|i, j| ((i * 8 + j) as f32 * 0.37).sin(), |_| 0.1);
+    net.l2 = DenseConnected::from_fn(|i, _| (i as f32 * 0.61).cos() * 0.5, |_| 0.0);
+
+    let q = Quantiser::default();
+    let mut trainer = Trainer::new(net, Adam::default(), Mse, Constant { lr: 0.01 }, 2);
+
+    let first = trainer
+        .train_batch_fake_quantised(&data, &q, 16, Default::default())
+        .unwrap();
+    let mut last = first;
+    for _ in 0..300 {
+        for batch in data.chunks(32) {
+            last = trainer
+                .train_batch_fake_quantised(batch, &q, 16, Default::default())
+                .unwrap();
+        }
+    }
+    assert!(last < first / 4.0, "loss went from {first} to {last}");
+
+    // the integer network reproduces the outputs seen in training
+    let net = trainer.network();
+    let quantised = net.quantise(&q, 16, Default::default()).unwrap();
+    let scale = quantised.output_scale() as f32;
+
+    for (input, _) in &data {
+        let (layers, _) = net
+            .out_with_layers_fake_quantised(input, &q, 16, Default::default())
+            .unwrap();
+        let actual = quantised.out(&SmallNet::quantise_input(&q, input, 16))[0] as f32 / scale;
+        assert!((layers.output_layer()[0] - actual).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn fake_quantised_training_with_scales() {
+    let data = [(Vector::from_raw([0.3, -0.7]), Vector::from_raw([0.5]))];
+    let net = Box::new(DenseConnected::<Identity, 2, 1>::from_fn(
+        |i, _| 0.1 * i as f32,
+        |_| 0.0,
+    ));
+
+    let q = Quantiser::default();
+    let mut trainer = Trainer::new(net, Adam::default(), Mse, Constant { lr: 0.01 }, 1);
+    assert!(matches!(
+        trainer.train_batch_fake_quantised(&data, &q, 16, Default::default()),
+        Err(QuantiseError::MissingScale { .. })
+    ));
+
+    let scales = Scales {
+        weights: Some(64),
+        output: None,
+    };
+    assert!(trainer
+        .train_batch_fake_quantised(&data, &q, 16, scales)
+        .is_ok());
+}
@@ -0,0 +1,110 @@
+use goober::{
+    activation::{Identity, ReLU},
+    layer::DenseConnected,
+    loss::Mse,
+    optimizer::Adam,
+    scheduler::{Constant, Cosine},
+    FeedForwardNetwork, Trainer, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: DenseConnected<ReLU, 2, 8>,
+    l2: DenseConnected<Identity, 8, 1>,
+}
+
+#[test]
+fn trainer_reduces_loss() {
+    let mut seed = 0x1234_5678_u32;
+    let mut rand = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed as f32 / u32::MAX as f32 - 0.5
+    };
+
+    let mut net = TestNet::boxed_and_zeroed();
+    net.l1 = DenseConnected::from_fn(|_, _| rand(), |_| 0.0);
+    net.l2 = DenseConnected::from_fn(|_, _| rand(), |_| 0.0);
+
+    let data = (0..256)
+        .map(|_| {
+            let (x, y) = (rand(), rand());
+            (Vector::from_raw([x, y]), Vector::from_raw([x * 2.0 - y]))
+        })
+        .collect::<Vec<_>>();
+
+    let mut trainer = Trainer::new(net, Adam::default(), Mse, Constant { lr: 0.01 }, 4);
+
+    let first = trainer.train_superbatch(data.chunks(32));
+    let mut last = first;
+    for _ in 0..50 {
+        last = trainer.train_superbatch(data.chunks(32));
+    }
+
+    assert_eq!(trainer.batches(), 51 * 8);
+    assert!(last < first / 10.0, "loss went from {first} to {last}");
+}
+
+#[test]
+fn resume_from_checkpoint() {
+    let data = (0..64)
+        .map(|i| {
+            let x = i as f32 / 64.0;
+            (Vector::from_raw([x, 1.0 - x]), Vector::from_raw([x]))
+        })
+        .collect::<Vec<_>>();
+
+    let mut net = TestNet::boxed_and_zeroed();
+    net.l1 = DenseConnected::from_fn(|i, j| ((i * 8 + j) as f32 * 0.37).sin(), |_| 0.0);
+    net.l2 = DenseConnected::from_fn(|i, _| (i as f32 * 0.61).cos(), |_| 0.0);
+
+    let schedule = Cosine {
+        max_lr: 0.01,
+        min_lr: 0.001,
+        steps: 16,
+    };
+    let mut trainer = Trainer::new(net, Adam::default(), Mse, schedule, 2);
+    trainer.train_superbatch(data.chunks(16));
+
+    let mut checkpoint = Vec::new();
+    trainer.save_checkpoint(&mut checkpoint).unwrap();
+
+    let mut resumed: Trainer<TestNet, _, _, Cosine> =
+        Trainer::load_checkpoint(&mut checkpoint.as_slice(), Adam::default(), Mse, 2).unwrap();
+    assert_eq!(resumed.batches(), 4);
+
+    let expected = trainer.train_superbatch(data.chunks(16));
+    let found = resumed.train_superbatch(data.chunks(16));
+    assert_eq!(expected, found);
+
+    let input = Vector::from_raw([0.3, 0.7]);
+    assert_eq!(trainer.network().out(&input), resumed.network().out(&input));
+}
+
+#[test]
+fn reject_oversized_step_count() {
+    let trainer = Trainer::new(
+        TestNet::boxed_and_zeroed(),
+        Adam::default(),
+        Mse,
+        Constant { lr: 0.01 },
+        1,
+    );
+
+    let mut checkpoint = Vec::new();
+    trainer.save_checkpoint(&mut checkpoint).unwrap();
+
+    let tag = checkpoint.windows(4).position(|w| w == b"OPTS").unwrap();
+    checkpoint[tag + 4..tag + 12].copy_from_slice(&u64::MAX.to_le_bytes());
+
+    let err = Trainer::<TestNet, _, _, Constant>::load_checkpoint(
+        &mut checkpoint.as_slice(),
+        Adam::default(),
+        Mse,
+        1,
+    )
+    .err()
+    .unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}